@@ -1,7 +1,11 @@
 use bevy::prelude::*;
 
-use crate::debug::DebugUpdateSet;
-use crate::instruction_screen::GameState;
+use crate::debug::{DebugState, DebugUpdateSet};
+use crate::instruction_screen::{GameRunSet, GameState};
+use crate::levels::{HALF_TILE_SIZE, TILE_SIZE};
+use crate::levels::data::LevelData;
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::player::Player;
 
 pub const DAY_COLOR: Color = Color::srgb(0.31, 0.75, 0.88);
 pub const NIGHT_COLOR: Color = Color::srgb(0., 0.11, 0.12);
@@ -13,7 +17,16 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_camera)
-            .add_systems(Update, move_camera.in_set(DebugUpdateSet))
+            .add_systems(
+                Update,
+                (
+                    move_camera.in_set(DebugUpdateSet),
+                    // the debug camera can be moved freely with the arrow keys
+                    follow_player
+                        .in_set(GameRunSet)
+                        .run_if(in_state(DebugState::Off)),
+                ),
+            )
             .add_systems(OnEnter(GameState::Play), game_start_camera);
     }
 }
@@ -38,6 +51,44 @@ fn game_start_camera(mut camera_query: Query<&mut Transform, With<Camera>>) {
     transform.scale = Vec3::splat(0.4);
 }
 
+/// Centers the camera on the player, without showing anything outside of the level. Levels smaller than the view sit in
+/// its bottom left corner, the way the camera framed every level before it followed the player
+fn follow_player(
+    mut camera_query: Query<(&mut Transform, &Camera), Without<Player>>,
+    player_query: Query<&Transform, With<Player>>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
+) {
+    let (mut camera_transform, camera) = camera_query.single_mut();
+
+    let (Ok(player_transform), Some(view_size)) =
+        (player_query.get_single(), camera.logical_viewport_size())
+    else {
+        return;
+    };
+
+    let Some(level_data) = level_data_handle
+        .0
+        .as_ref()
+        .and_then(|handle| level_data_assets.get(handle))
+    else {
+        return;
+    };
+
+    let half_view_size = view_size * camera_transform.scale.truncate() / 2.;
+
+    // tiles are positioned by their center, so the level starts half a tile left of and below the origin
+    let level_min = Vec2::splat(-HALF_TILE_SIZE);
+    let level_max = level_min + level_data.size().as_vec2() * TILE_SIZE;
+
+    let min = level_min + half_view_size;
+    let max = level_max - half_view_size;
+    let target = player_transform.translation.truncate();
+
+    camera_transform.translation.x = target.x.min(max.x).max(min.x);
+    camera_transform.translation.y = target.y.min(max.y).max(min.y);
+}
+
 fn move_camera(
     mut camera_query: Query<&mut Transform, With<Camera2d>>,
     keys: Res<ButtonInput<KeyCode>>,
@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::Path;
+
+use crate::levels::format::LevelFormat;
+use crate::levels::registry::read_tile_registry_from_disk;
+
+/// Converts a level file into another format, both formats are picked from the file extensions. Runs without creating
+/// the bevy app, like the level lint
+pub fn convert_level(args: Vec<String>) -> bool {
+    let [input, output] = args.as_slice() else {
+        println!("Usage: convert-level <input> <output>");
+        return false;
+    };
+
+    let (input, output) = (Path::new(input), Path::new(output));
+
+    let (Some(input_format), Some(output_format)) = (
+        LevelFormat::from_path(input),
+        LevelFormat::from_path(output),
+    ) else {
+        println!(
+            "Level files must end in one of: {}",
+            LevelFormat::ALL
+                .iter()
+                .map(|format| format!(".{}", format.extension()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        return false;
+    };
+
+    let bytes = match fs::read(input) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            println!("Could not read {}: {}", input.display(), err);
+            return false;
+        }
+    };
+
+    // the text formats write tiles with the codes and glyphs the registry gives them
+    let tile_registry = match read_tile_registry_from_disk() {
+        Ok(tile_registry) => tile_registry,
+        Err(err) => {
+            println!("Could not load tile registry: {}", err);
+            return false;
+        }
+    };
+
+    let converted = input_format
+        .deserialize(bytes.as_slice(), &tile_registry)
+        .and_then(|level_data| output_format.serialize(&level_data, &tile_registry));
+
+    let text = match converted {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Could not convert {}: {}", input.display(), err);
+            return false;
+        }
+    };
+
+    if let Err(err) = fs::write(output, text) {
+        println!("Could not write {}: {}", output.display(), err);
+        return false;
+    }
+
+    println!("Converted {} to {}", input.display(), output.display());
+
+    true
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::day_night::sun::DayClock;
+use crate::levels::data::{CloudData, LevelData};
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::levels::TileLevelLoadedEvent;
+use crate::z_indices::CLOUD_Z_INDEX;
+
+pub struct CloudPlugin;
+
+impl Plugin for CloudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_clouds, move_clouds).chain());
+    }
+}
+
+/// Drifts along its path with the day clock, so every run sees the clouds in the same places at the same times. Its
+/// sensor has no [`SunTransmittance`](crate::levels::SunTransmittance), so the sun rays that hit it are blocked
+#[derive(Component)]
+pub struct Cloud(pub CloudData);
+
+fn spawn_clouds(
+    mut commands: Commands,
+    cloud_query: Query<Entity, With<Cloud>>,
+    mut tile_level_loaded_event: EventReader<TileLevelLoadedEvent>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
+    asset_server: Res<AssetServer>,
+) {
+    if tile_level_loaded_event.read().next().is_none() {
+        return;
+    }
+
+    for entity in cloud_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(level_data) = level_data_handle
+        .0
+        .as_ref()
+        .and_then(|handle| level_data_assets.get(handle))
+    else {
+        return;
+    };
+
+    for cloud in &level_data.clouds {
+        let size = cloud.size();
+
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("image/object/cloud.png"),
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(cloud.position_at(0.).extend(CLOUD_Z_INDEX)),
+                ..default()
+            },
+            Collider::cuboid(size.x / 2., size.y / 2.),
+            Sensor,
+            Cloud(cloud.clone()),
+        ));
+    }
+}
+
+fn move_clouds(mut cloud_query: Query<(&mut Transform, &Cloud)>, day_clock: Res<DayClock>) {
+    for (mut transform, cloud) in cloud_query.iter_mut() {
+        let position = cloud.0.position_at(day_clock.0);
+
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
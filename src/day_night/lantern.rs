@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+use crate::camera::NIGHT_COLOR;
+use crate::day_night::DayNightState;
+use crate::day_night::transition::DayNightTransition;
+use crate::levels::{HALF_TILE_SIZE, TILE_SIZE, TileLevelLoadedEvent};
+use crate::levels::data::LevelData;
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::levels::registry::TileRegistry;
+use crate::math::tile_pos_to_world_pos_2d;
+use crate::player::Player;
+use crate::z_indices::DARKNESS_Z_INDEX;
+
+/// Distance between the vertices of the darkness, the light fades linearly between them so smaller is smoother
+const DARKNESS_CELL_SIZE: f32 = HALF_TILE_SIZE;
+/// Tiles of darkness past each edge of the level, enough to cover the view around levels smaller than it
+const DARKNESS_MARGIN: f32 = 10.;
+/// Alpha of the darkness away from any light
+const DARKNESS_ALPHA: f32 = 0.95;
+/// Tiles past the edge of a light over which it fades into the dark
+const LIGHT_FALLOFF: f32 = 2.;
+
+pub struct LanternPlugin;
+
+impl Plugin for LanternPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                create_darkness.run_if(resource_exists::<TileRegistry>),
+                light_darkness,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Covers the level at night, except for a circle of light around the snowman and around the tiles that give off
+/// light
+#[derive(Component)]
+struct Darkness {
+    /// Number of vertices along each side
+    vertex_count: UVec2,
+    /// World position of the bottom left vertex
+    origin: Vec2,
+    /// World position and radius in tiles of the light of every tile that gives off light
+    tile_lights: Vec<(Vec2, f32)>,
+}
+
+impl Darkness {
+    fn vertex_pos(&self, index: usize) -> Vec2 {
+        let x = index as u32 % self.vertex_count.x;
+        let y = index as u32 / self.vertex_count.x;
+
+        self.origin + UVec2::new(x, y).as_vec2() * DARKNESS_CELL_SIZE
+    }
+}
+
+/// How lit a position is by a light, 1 inside its radius, fading to 0 past it
+fn light_at(pos: Vec2, light_pos: Vec2, radius: f32) -> f32 {
+    let distance = pos.distance(light_pos) / TILE_SIZE;
+
+    ((radius + LIGHT_FALLOFF - distance) / LIGHT_FALLOFF).clamp(0., 1.)
+}
+
+fn create_darkness(
+    mut commands: Commands,
+    darkness_query: Query<Entity, With<Darkness>>,
+    mut tile_level_loaded_event: EventReader<TileLevelLoadedEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tile_registry: Res<TileRegistry>,
+    day_night_state: Res<State<DayNightState>>,
+) {
+    let Some(tile_level_loaded) = tile_level_loaded_event.read().next() else {
+        return;
+    };
+
+    for entity in darkness_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let tile_lights = tile_level_loaded
+        .level_data_map
+        .iter()
+        .filter_map(|(loc, tile_type)| {
+            tile_registry
+                .light(tile_type)
+                .map(|radius| (tile_pos_to_world_pos_2d((*loc).into()), radius))
+        })
+        .collect();
+
+    let margin = DARKNESS_MARGIN * TILE_SIZE;
+    // tiles are positioned by their center, so the level starts half a tile left of and below the origin
+    let min = Vec2::splat(-HALF_TILE_SIZE - margin);
+    let size = tile_level_loaded.size.as_vec2() * TILE_SIZE + margin * 2.;
+    let cell_count = (size / DARKNESS_CELL_SIZE).ceil().as_uvec2();
+
+    let darkness = Darkness {
+        vertex_count: cell_count + 1,
+        origin: min,
+        tile_lights,
+    };
+
+    let vertex_total = (darkness.vertex_count.x * darkness.vertex_count.y) as usize;
+
+    let positions = (0..vertex_total)
+        .map(|index| darkness.vertex_pos(index).extend(0.).to_array())
+        .collect::<Vec<_>>();
+
+    let mut indices = Vec::new();
+
+    for y in 0..cell_count.y {
+        for x in 0..cell_count.x {
+            let bottom_left = y * darkness.vertex_count.x + x;
+            let bottom_right = bottom_left + 1;
+            let top_left = bottom_left + darkness.vertex_count.x;
+            let top_right = top_left + 1;
+
+            indices.extend([
+                bottom_left,
+                bottom_right,
+                top_right,
+                bottom_left,
+                top_right,
+                top_left,
+            ]);
+        }
+    }
+
+    // the colors are filled in every frame of the night, by the lights around
+    let mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, vec![[0., 0., 0., 0.]; vertex_total])
+    .with_inserted_indices(Indices::U32(indices));
+
+    let visibility = match day_night_state.get() {
+        DayNightState::Day => Visibility::Hidden,
+        DayNightState::Night => Visibility::Visible,
+    };
+
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(mesh).into(),
+            material: materials.add(Color::WHITE),
+            transform: Transform::from_xyz(0., 0., DARKNESS_Z_INDEX),
+            visibility,
+            ..default()
+        },
+        darkness,
+    ));
+}
+
+/// The darkness closes in as the sky fades, leaving the snowman only what its own light and the tiles around it show,
+/// and lifts again with the sky at dawn. It is only hidden once the sky has finished fading into the day
+fn light_darkness(
+    mut darkness_query: Query<(&Darkness, &Mesh2dHandle, &mut Visibility)>,
+    player_query: Query<&Transform, With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
+    transition: Res<DayNightTransition>,
+) {
+    let Ok((darkness, mesh_handle, mut visibility)) = darkness_query.get_single_mut() else {
+        return;
+    };
+
+    let daylight = transition.daylight();
+
+    if daylight >= 1. {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    let Some(mesh) = meshes.get_mut(&mesh_handle.0) else {
+        return;
+    };
+
+    let sight_radius = level_data_handle
+        .0
+        .as_ref()
+        .and_then(|handle| level_data_assets.get(handle))
+        .map_or(LevelData::default_sight_radius(), |level_data| {
+            level_data.sight_radius
+        });
+
+    let player_pos = player_query.single().translation.truncate();
+    let night_color = LinearRgba::from(NIGHT_COLOR);
+    let darkness_alpha = DARKNESS_ALPHA * (1. - daylight);
+
+    let colors = (0..(darkness.vertex_count.x * darkness.vertex_count.y) as usize)
+        .map(|index| {
+            let vertex_pos = darkness.vertex_pos(index);
+
+            let light = darkness
+                .tile_lights
+                .iter()
+                .map(|(light_pos, radius)| light_at(vertex_pos, *light_pos, *radius))
+                .fold(light_at(vertex_pos, player_pos, sight_radius), f32::max);
+
+            [
+                night_color.red,
+                night_color.green,
+                night_color.blue,
+                darkness_alpha * (1. - light),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+
+    *visibility = Visibility::Visible;
+}
@@ -1,27 +1,42 @@
 use bevy::prelude::*;
 
 use crate::camera::{DAY_COLOR, NIGHT_COLOR};
+use crate::day_night::cloud::CloudPlugin;
+use crate::day_night::lantern::LanternPlugin;
 use crate::day_night::music::MusicPlugin;
 use crate::day_night::shadow::ShadowPlugin;
+use crate::day_night::sun::SunPlugin;
+use crate::day_night::transition::{DayNightTransition, TransitionPlugin};
 
+pub mod cloud;
+mod lantern;
 mod music;
 pub mod shadow;
+pub mod sun;
+pub mod transition;
 
 pub struct DayNightPlugin;
 
 impl Plugin for DayNightPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((ShadowPlugin, MusicPlugin))
-            .init_state::<DayNightState>()
-            .add_event::<SetDayNightEvent>()
-            .configure_sets(
-                Update,
-                (
-                    NightCycleSet.run_if(in_state(DayNightState::Night)),
-                    DayCycleSet.run_if(in_state(DayNightState::Day)),
-                ),
-            )
-            .add_systems(Update, set_day_night_cycle);
+        app.add_plugins((
+            ShadowPlugin,
+            CloudPlugin,
+            LanternPlugin,
+            SunPlugin,
+            TransitionPlugin,
+            MusicPlugin,
+        ))
+        .init_state::<DayNightState>()
+        .add_event::<SetDayNightEvent>()
+        .configure_sets(
+            Update,
+            (
+                NightCycleSet.run_if(in_state(DayNightState::Night)),
+                DayCycleSet.run_if(in_state(DayNightState::Day)),
+            ),
+        )
+        .add_systems(Update, (set_day_night_cycle, update_sky_color));
     }
 }
 
@@ -45,21 +60,20 @@ pub enum DayNightState {
 pub struct SetDayNightEvent(pub DayNightState);
 
 fn set_day_night_cycle(
-    mut camera_query: Query<&mut Camera>,
     mut set_day_night_ev: EventReader<SetDayNightEvent>,
     mut day_night_cycle_next: ResMut<NextState<DayNightState>>,
 ) {
-    let mut camera = camera_query.single_mut();
-
     if let Some(set_day_night) = set_day_night_ev.read().next() {
-        match set_day_night.0 {
-            DayNightState::Day => {
-                camera.clear_color = ClearColorConfig::Custom(DAY_COLOR);
-            }
-            DayNightState::Night => {
-                camera.clear_color = ClearColorConfig::Custom(NIGHT_COLOR);
-            }
-        }
         day_night_cycle_next.set(set_day_night.0);
     }
 }
+
+fn update_sky_color(mut camera_query: Query<&mut Camera>, transition: Res<DayNightTransition>) {
+    let mut camera = camera_query.single_mut();
+
+    let sky_color = NIGHT_COLOR
+        .to_srgba()
+        .mix(&DAY_COLOR.to_srgba(), transition.daylight());
+
+    camera.clear_color = ClearColorConfig::Custom(sky_color.into());
+}
@@ -1,18 +1,14 @@
 use bevy::audio::{PlaybackMode, Volume};
 use bevy::prelude::*;
 
-use crate::day_night::DayNightState;
-
-const FADE_TIME: f32 = 4.;
+use crate::day_night::transition::{DayNightTransition, FADE_TIME};
 
 pub struct MusicPlugin;
 
 impl Plugin for MusicPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_music)
-            .add_systems(Update, fade_audio)
-            .add_systems(OnEnter(DayNightState::Night), night_music)
-            .add_systems(OnEnter(DayNightState::Day), day_music);
+            .add_systems(Update, update_music_volume);
     }
 }
 
@@ -22,21 +18,6 @@ struct NightAudio;
 #[derive(Component)]
 struct DayAudio;
 
-#[derive(Component)]
-enum FadeAudioDirection {
-    Increasing,
-    Decreasing,
-}
-
-impl FadeAudioDirection {
-    fn get_fade_multiplier(&self) -> f32 {
-        match self {
-            FadeAudioDirection::Increasing => 1.,
-            FadeAudioDirection::Decreasing => -1.,
-        }
-    }
-}
-
 fn setup_music(mut commands: Commands, asset_server: Res<AssetServer>) {
     let day_handle = asset_server.load::<AudioSource>("audio/music/day.mp3");
     let night_handle = asset_server.load::<AudioSource>("audio/music/night.mp3");
@@ -52,7 +33,6 @@ fn setup_music(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
         },
         DayAudio,
-        FadeAudioDirection::Increasing,
     ));
 
     commands.spawn((
@@ -69,69 +49,39 @@ fn setup_music(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
-fn night_music(
-    mut commands: Commands,
-    day_audio_query: Query<Entity, With<DayAudio>>,
-    night_audio_query: Query<Entity, With<NightAudio>>,
+/// The day and night tracks crossfade along with the sky and the shadows. The music also fades in over the fade time
+/// once it has loaded, as the game starts out in the day without a transition to fade it
+fn update_music_volume(
+    day_audio_query: Query<&AudioSink, With<DayAudio>>,
+    night_audio_query: Query<&AudioSink, With<NightAudio>>,
+    transition: Res<DayNightTransition>,
+    time: Res<Time>,
+    mut fade_in: Local<f32>,
 ) {
-    if let Ok(night_audio_entity) = night_audio_query.get_single() {
-        commands
-            .entity(night_audio_entity)
-            .insert(FadeAudioDirection::Increasing);
+    if day_audio_query.is_empty() && night_audio_query.is_empty() {
+        return;
     }
 
-    if let Ok(day_audio_entity) = day_audio_query.get_single() {
-        commands
-            .entity(day_audio_entity)
-            .insert(FadeAudioDirection::Decreasing);
-    }
-}
+    *fade_in = (*fade_in + time.delta_seconds() / FADE_TIME).min(1.);
 
-fn day_music(
-    mut commands: Commands,
-    night_audio_query: Query<Entity, With<NightAudio>>,
-    day_audio_query: Query<Entity, With<DayAudio>>,
-) {
-    if let Ok(night_audio_entity) = night_audio_query.get_single() {
-        commands
-            .entity(night_audio_entity)
-            .insert(FadeAudioDirection::Decreasing);
+    let daylight = transition.daylight();
+
+    for audio_sink in day_audio_query.iter() {
+        set_track_volume(audio_sink, daylight * *fade_in);
     }
 
-    if let Ok(day_audio_entity) = day_audio_query.get_single() {
-        commands
-            .entity(day_audio_entity)
-            .insert(FadeAudioDirection::Increasing);
+    for audio_sink in night_audio_query.iter() {
+        set_track_volume(audio_sink, (1. - daylight) * *fade_in);
     }
 }
 
-fn fade_audio(
-    mut commands: Commands,
-    audio_query: Query<(Entity, &AudioSink, &FadeAudioDirection)>,
-    time: Res<Time>,
-) {
-    for (entity, audio_sink, fade_audio_direction) in audio_query.iter() {
-        let volume = (audio_sink.volume()
-            + (time.delta_seconds() / FADE_TIME * fade_audio_direction.get_fade_multiplier()))
-        .clamp(0., 1.);
-
-        audio_sink.set_volume(volume);
+/// Tracks that can not be heard are paused, so they carry on where they left off when they fade back in
+fn set_track_volume(audio_sink: &AudioSink, volume: f32) {
+    audio_sink.set_volume(volume);
 
-        match fade_audio_direction {
-            FadeAudioDirection::Increasing => {
-                // println!("inc {}");
-                audio_sink.play();
-                if volume >= 1. {
-                    commands.entity(entity).remove::<FadeAudioDirection>();
-                }
-            }
-            FadeAudioDirection::Decreasing => {
-                // println!("dec {}");
-                if volume <= 0. {
-                    audio_sink.pause();
-                    commands.entity(entity).remove::<FadeAudioDirection>();
-                }
-            }
-        }
+    if volume > 0. {
+        audio_sink.play();
+    } else {
+        audio_sink.pause();
     }
 }
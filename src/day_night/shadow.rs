@@ -1,113 +1,334 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use bevy::utils::HashMap;
 
 use crate::camera::NIGHT_COLOR;
-use crate::day_night::DayNightState;
-use crate::levels::{TILE_MAP_SIZE, TILE_SIZE, TileLevelLoadedEvent};
+use crate::day_night::cloud::Cloud;
+use crate::day_night::sun::SunDirection;
+use crate::day_night::transition::DayNightTransition;
+use crate::levels::{HALF_TILE_SIZE, TILE_SIZE, TileLevelLoadedEvent};
 use crate::levels::data::LocationData;
-use crate::math::tile_pos_to_world_pos_2d;
-use crate::z_indices::SHADOW_Z_INDEX;
+use crate::levels::registry::TileRegistry;
+use crate::z_indices::{CLOUD_Z_INDEX, SHADOW_Z_INDEX};
 
+/// Alpha of the shadow below tiles that block all of the sun, partial shadows are lighter
 const SHADOW_ALPHA: f32 = 0.2;
 
+/// Shadows are rebuilt each time the sun moves by this angle, rather than every frame
+const SHADOW_ANGLE_STEP: f32 = PI / 90.;
+
 pub struct ShadowPlugin;
 
 impl Plugin for ShadowPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ShadowMaterial>()
-            .add_systems(Startup, create_shadow_material)
-            .add_systems(Update, create_shadows)
-            .add_systems(OnEnter(DayNightState::Night), hide_shadows_night)
-            .add_systems(OnEnter(DayNightState::Day), show_shadows_day);
+        app.init_resource::<ShadowMaterials>()
+            .init_resource::<ShadowCasters>()
+            .add_systems(
+                Update,
+                (
+                    update_shadow_casters.run_if(resource_exists::<TileRegistry>),
+                    create_shadows,
+                    (create_cloud_shadows, stretch_cloud_shadows).chain(),
+                    fade_shadows,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// One material per amount of sunlight reaching a shadow, created the first time a level needs it
+#[derive(Resource)]
+struct ShadowMaterials {
+    materials: HashMap<u32, Handle<ColorMaterial>>,
+    /// Daylight the materials were last faded to, shadows fade out with the sky
+    daylight: f32,
+}
+
+impl Default for ShadowMaterials {
+    fn default() -> Self {
+        Self {
+            materials: HashMap::new(),
+            daylight: 1.,
+        }
+    }
+}
+
+impl ShadowMaterials {
+    fn get_or_add(
+        &mut self,
+        sunlight: f32,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        let daylight = self.daylight;
+
+        self.materials
+            .entry(sunlight.to_bits())
+            .or_insert_with(|| materials.add(shadow_color(sunlight, daylight)))
+            .clone()
+    }
+}
+
+fn shadow_color(sunlight: f32, daylight: f32) -> Color {
+    let mut shadow_color = NIGHT_COLOR;
+    shadow_color.set_alpha(SHADOW_ALPHA * (1. - sunlight) * daylight);
+
+    shadow_color
+}
+
+fn shadow_visibility(daylight: f32) -> Visibility {
+    if daylight > 0. {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
     }
 }
 
+/// Sun transmittance of the tiles of the current level that take some of the sun, kept so the shadows can be rebuilt
+/// as the sun moves
 #[derive(Resource, Default)]
-struct ShadowMaterial(Handle<ColorMaterial>);
+struct ShadowCasters {
+    tiles: HashMap<LocationData, f32>,
+    size: UVec2,
+    /// Sun angle the shadows were last built for, in steps of [`SHADOW_ANGLE_STEP`], none when they need rebuilding
+    built_step: Option<i32>,
+}
+
+impl ShadowCasters {
+    fn sun_transmittance(&self, x: i32, y: u32) -> f32 {
+        if x < 0 {
+            return 1.;
+        }
+
+        self.tiles
+            .get(&LocationData::new(x as u32, y))
+            .copied()
+            .unwrap_or(1.)
+    }
+}
 
 #[derive(Component)]
 struct Shadow;
 
-fn create_shadow_material(
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut shadow_material: ResMut<ShadowMaterial>,
-) {
-    let mut shadow_color = NIGHT_COLOR;
-    shadow_color.set_alpha(SHADOW_ALPHA);
+/// Shadow of a cloud, a child of it which is stretched down to the bottom of the level along the sun every frame
+#[derive(Component)]
+struct CloudShadow;
 
-    shadow_material.0 = materials.add(shadow_color);
+/// Corners go bottom left, bottom right, top right and top left
+fn parallelogram_mesh(corners: Vec<[f32; 3]>) -> Mesh {
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, corners)
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_UV_0,
+        vec![[0., 1.], [1., 1.], [1., 0.], [0., 0.]],
+    )
+    .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]))
 }
 
+fn update_shadow_casters(
+    mut tile_level_loaded_event: EventReader<TileLevelLoadedEvent>,
+    mut shadow_casters: ResMut<ShadowCasters>,
+    tile_registry: Res<TileRegistry>,
+) {
+    if let Some(tile_level_loaded) = tile_level_loaded_event.read().next() {
+        // tiles that let all of the sun through are drawn over, as if they were not there
+        shadow_casters.tiles = tile_level_loaded
+            .level_data_map
+            .iter()
+            .map(|(loc, tile_type)| (*loc, tile_registry.sun_transmittance(tile_type)))
+            .filter(|(_, sun_transmittance)| *sun_transmittance < 1.)
+            .collect();
+        shadow_casters.size = tile_level_loaded.size;
+        shadow_casters.built_step = None;
+    }
+}
+
+/// The sunlight travels down slanted strips one tile wide, which start along the top of the level. Each strip is
+/// followed down row by row, and the runs of rows between tiles that take some of the sun become one parallelogram
 fn create_shadows(
     mut commands: Commands,
-    mut tile_level_loaded_event: EventReader<TileLevelLoadedEvent>,
-    shadow_query: Query<Entity, With<Shadow>>,
+    shadow_query: Query<Entity, (With<Shadow>, Without<CloudShadow>)>,
     mut meshes: ResMut<Assets<Mesh>>,
-    shadow_material: Res<ShadowMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shadow_materials: ResMut<ShadowMaterials>,
+    mut shadow_casters: ResMut<ShadowCasters>,
+    sun_direction: Res<SunDirection>,
 ) {
-    if let Some(tile_level_loaded) = tile_level_loaded_event.read().next() {
-        for entity in shadow_query.iter() {
-            commands.entity(entity).despawn();
-        }
+    let sun_step = (sun_direction.0.x.atan2(sun_direction.0.y) / SHADOW_ANGLE_STEP).round() as i32;
 
-        let tile_map = &tile_level_loaded.level_data_map;
+    if shadow_casters.built_step == Some(sun_step) {
+        return;
+    }
 
-        for x in 0..TILE_MAP_SIZE {
-            // start at index of 1, because no need to cast shadow where camera does not see (beyond extents of game)
-            for y in 1..TILE_MAP_SIZE {
-                if !tile_map.contains_key(&LocationData::new(x, y)) {
-                    continue;
-                }
+    shadow_casters.built_step = Some(sun_step);
 
-                let mut shadow_len = 0;
-                let mut cast_y = y;
+    for entity in shadow_query.iter() {
+        commands.entity(entity).despawn();
+    }
 
-                while cast_y > 0 {
-                    cast_y -= 1;
+    let size = shadow_casters.size;
 
-                    if tile_map.contains_key(&LocationData::new(x, cast_y)) {
-                        break;
-                    }
+    if size.y == 0 {
+        return;
+    }
 
-                    shadow_len += 1;
-                }
+    let visibility = shadow_visibility(shadow_materials.daylight);
 
-                if shadow_len == 0 {
-                    continue;
-                }
+    // how far the light moves right for every pixel it goes down
+    let slope = -(sun_step as f32 * SHADOW_ANGLE_STEP).tan();
+    let top_row = size.y - 1;
+    let top_y = top_row as f32 * TILE_SIZE;
+    // how far right the light has moved by the time it reaches the bottom row, in tiles
+    let drift = slope * top_row as f32;
+
+    // enough strips to cover every column of both the top and the bottom row
+    let first_strip = (-drift).min(0.).floor() as i32 - 1;
+    let last_strip = size.x as i32 + (-drift).max(0.).ceil() as i32;
+
+    for strip in first_strip..=last_strip {
+        let strip_x = strip as f32 * TILE_SIZE;
+        let center_x_at = |y: f32| strip_x - (y - top_y) * slope;
 
-                let start_pos = UVec2::new(x, y - 1);
-                let end_pos = start_pos - UVec2::new(0, shadow_len - 1);
+        let mut spawn_shadow = |top: u32, bottom: u32, sunlight: f32| {
+            let top_edge = top as f32 * TILE_SIZE + HALF_TILE_SIZE;
+            let bottom_edge = bottom as f32 * TILE_SIZE - HALF_TILE_SIZE;
+            let top_x = center_x_at(top_edge);
+            let bottom_x = center_x_at(bottom_edge);
 
-                let world_start_pos = tile_pos_to_world_pos_2d(start_pos);
-                let world_end_pos = tile_pos_to_world_pos_2d(end_pos);
+            let mesh = parallelogram_mesh(vec![
+                [bottom_x - HALF_TILE_SIZE, bottom_edge, 0.],
+                [bottom_x + HALF_TILE_SIZE, bottom_edge, 0.],
+                [top_x + HALF_TILE_SIZE, top_edge, 0.],
+                [top_x - HALF_TILE_SIZE, top_edge, 0.],
+            ]);
 
-                let midpoint = ((world_start_pos + world_end_pos) / 2.).extend(SHADOW_Z_INDEX);
+            commands.spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(mesh).into(),
+                    material: shadow_materials.get_or_add(sunlight, &mut materials),
+                    transform: Transform::from_xyz(0., 0., SHADOW_Z_INDEX),
+                    visibility,
+                    ..default()
+                },
+                Shadow,
+            ));
+        };
 
-                let shadow_half_size = Vec2::new(TILE_SIZE, shadow_len as f32 * TILE_SIZE);
+        let mut sunlight = 1.;
+        // top row of the shadow being built, it ends at the next tile that takes some of the sun
+        let mut shadow_top = None;
 
-                commands.spawn((
-                    MaterialMesh2dBundle {
-                        mesh: meshes.add(Rectangle::from_size(shadow_half_size)).into(),
-                        material: shadow_material.0.clone(),
-                        transform: Transform::from_translation(midpoint),
-                        ..default()
-                    },
-                    Shadow,
-                ));
+        for y in (0..size.y).rev() {
+            let column = (center_x_at(y as f32 * TILE_SIZE) / TILE_SIZE).round() as i32;
+            let sun_transmittance = shadow_casters.sun_transmittance(column, y);
+
+            if sun_transmittance < 1. {
+                if let Some(top) = shadow_top.take() {
+                    spawn_shadow(top, y + 1, sunlight);
+                }
+
+                sunlight *= sun_transmittance;
+            } else if sunlight < 1. && shadow_top.is_none() {
+                shadow_top = Some(y);
             }
         }
+
+        if let Some(top) = shadow_top {
+            spawn_shadow(top, 0, sunlight);
+        }
+    }
+}
+
+fn create_cloud_shadows(
+    mut commands: Commands,
+    cloud_query: Query<Entity, Added<Cloud>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shadow_materials: ResMut<ShadowMaterials>,
+) {
+    for cloud_entity in cloud_query.iter() {
+        // the corners are only known once the cloud has moved under the sun
+        let mesh = parallelogram_mesh(vec![[0., 0., 0.]; 4]);
+
+        let shadow_entity = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(mesh).into(),
+                    material: shadow_materials.get_or_add(0., &mut materials),
+                    transform: Transform::from_xyz(0., 0., SHADOW_Z_INDEX - CLOUD_Z_INDEX),
+                    visibility: shadow_visibility(shadow_materials.daylight),
+                    ..default()
+                },
+                Shadow,
+                CloudShadow,
+            ))
+            .id();
+
+        commands.entity(cloud_entity).add_child(shadow_entity);
     }
 }
 
-fn hide_shadows_night(mut shadow_query: Query<&mut Visibility, With<Shadow>>) {
-    for mut visibility in shadow_query.iter_mut() {
-        *visibility = Visibility::Hidden;
+fn stretch_cloud_shadows(
+    cloud_query: Query<(&Transform, &Cloud, &Children)>,
+    cloud_shadow_query: Query<&Mesh2dHandle, With<CloudShadow>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    sun_direction: Res<SunDirection>,
+) {
+    // how far the light moves right for every pixel it goes down
+    let slope = -sun_direction.0.x / sun_direction.0.y;
+
+    for (transform, cloud, children) in cloud_query.iter() {
+        let half_size = cloud.0.size() / 2.;
+        let top = -half_size.y;
+        // the bottom of the level, relative to the cloud
+        let bottom = (-HALF_TILE_SIZE - transform.translation.y).min(top);
+        let shift = (top - bottom) * slope;
+
+        for mesh_handle in cloud_shadow_query.iter_many(children) {
+            if let Some(mesh) = meshes.get_mut(&mesh_handle.0) {
+                mesh.insert_attribute(
+                    Mesh::ATTRIBUTE_POSITION,
+                    vec![
+                        [shift - half_size.x, bottom, 0.],
+                        [shift + half_size.x, bottom, 0.],
+                        [half_size.x, top, 0.],
+                        [-half_size.x, top, 0.],
+                    ],
+                );
+            }
+        }
     }
 }
 
-fn show_shadows_day(mut shadow_query: Query<&mut Visibility, With<Shadow>>) {
-    for mut visibility in shadow_query.iter_mut() {
-        *visibility = Visibility::Visible;
+fn fade_shadows(
+    mut shadow_query: Query<&mut Visibility, With<Shadow>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shadow_materials: ResMut<ShadowMaterials>,
+    transition: Res<DayNightTransition>,
+) {
+    let daylight = transition.daylight();
+
+    if shadow_materials.daylight == daylight {
+        return;
+    }
+
+    shadow_materials.daylight = daylight;
+
+    for (sunlight, handle) in &shadow_materials.materials {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = shadow_color(f32::from_bits(*sunlight), daylight);
+        }
+    }
+
+    let visibility = shadow_visibility(daylight);
+
+    for mut shadow in shadow_query.iter_mut() {
+        *shadow = visibility;
     }
 }
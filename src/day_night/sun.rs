@@ -0,0 +1,183 @@
+use std::f32::consts::FRAC_PI_3;
+
+use bevy::prelude::*;
+
+use crate::day_night::{DayNightState, SetDayNightEvent};
+use crate::instruction_screen::GameRunSet;
+use crate::levels::data::{DayNightCycleData, LevelData};
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::player::respawn::RespawnPlayerEvent;
+
+/// Angle between the sun and straight up at sunrise and sunset, the sun rises on the left and sets on the right
+const SUN_MAX_ANGLE: f32 = FRAC_PI_3;
+/// Seconds the sky takes to darken before sunset, and to light up again before sunrise, in levels with a timed cycle.
+/// Days too short for it get a twilight half as long as the day instead
+const TWILIGHT_LENGTH: f32 = 8.;
+
+pub struct SunPlugin;
+
+impl Plugin for SunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayClock>()
+            .init_resource::<SunDirection>()
+            .init_resource::<SunIntensity>()
+            .add_systems(
+                Update,
+                (
+                    reset_day_clock,
+                    advance_day_clock.in_set(GameRunSet),
+                    update_sun,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Seconds since the sun rose on the current run
+#[derive(Resource, Default)]
+pub struct DayClock(pub f32);
+
+/// Unit vector pointing from the level towards the sun
+#[derive(Resource)]
+pub struct SunDirection(pub Vec2);
+
+impl Default for SunDirection {
+    fn default() -> Self {
+        Self(sun_direction(0., LevelData::default_day_length()))
+    }
+}
+
+/// How bright the sun is, from 0 at night to 1 during the day. Levels with a timed cycle fade between the two at dusk
+/// and dawn, the others switch at once
+#[derive(Resource)]
+pub struct SunIntensity(pub f32);
+
+impl Default for SunIntensity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Direction towards the sun the given number of seconds after sunrise, sweeping evenly across the sky until the day
+/// length is over
+pub fn sun_direction(elapsed: f32, day_length: f32) -> Vec2 {
+    let progress = (elapsed / day_length).clamp(0., 1.);
+    let angle = SUN_MAX_ANGLE * (2. * progress - 1.);
+
+    Vec2::new(angle.sin(), angle.cos())
+}
+
+/// Direction towards the sun and its intensity the given number of seconds into a run of the level. Only levels with
+/// the per run cycle need to know which run it is, the timed cycle works it out from the time
+pub fn sun_at(level_data: &LevelData, elapsed: f32, run: DayNightState) -> (Vec2, f32) {
+    let day_length = level_data.day_length;
+
+    match level_data.day_night_cycle {
+        DayNightCycleData::PerRun => {
+            let intensity = match run {
+                DayNightState::Day => 1.,
+                DayNightState::Night => 0.,
+            };
+
+            (sun_direction(elapsed, day_length), intensity)
+        }
+        DayNightCycleData::Timed => {
+            let time_of_day = elapsed.rem_euclid(day_length * 2.);
+            // the dusk has to leave the sun at full strength at sunrise, where the dawn left it
+            let twilight_length = TWILIGHT_LENGTH.min(day_length / 2.);
+
+            if time_of_day < day_length {
+                let intensity = ((day_length - time_of_day) / twilight_length).min(1.);
+
+                (sun_direction(time_of_day, day_length), intensity)
+            } else {
+                // the sun waits below the horizon where it will rise, so it is already there when the dawn lights it
+                let intensity = ((time_of_day - day_length * 2.) / twilight_length + 1.).max(0.);
+
+                (sun_direction(0., day_length), intensity)
+            }
+        }
+    }
+}
+
+/// Whether it is day or night the given number of seconds into a run of a level with a timed cycle
+fn timed_day_night(elapsed: f32, day_length: f32) -> DayNightState {
+    if elapsed.rem_euclid(day_length * 2.) < day_length {
+        DayNightState::Day
+    } else {
+        DayNightState::Night
+    }
+}
+
+/// Every run, day or night, starts again at sunrise
+fn reset_day_clock(
+    mut respawn_player_event: EventReader<RespawnPlayerEvent>,
+    mut day_clock: ResMut<DayClock>,
+) {
+    if respawn_player_event.read().next().is_some() {
+        day_clock.0 = 0.;
+    }
+}
+
+fn advance_day_clock(mut day_clock: ResMut<DayClock>, time: Res<Time>) {
+    day_clock.0 += time.delta_seconds();
+}
+
+fn update_sun(
+    day_clock: Res<DayClock>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
+    day_night_state: Res<State<DayNightState>>,
+    mut set_day_night: EventWriter<SetDayNightEvent>,
+    mut sun_direction_res: ResMut<SunDirection>,
+    mut sun_intensity: ResMut<SunIntensity>,
+) {
+    let Some(level_data) = level_data_handle
+        .0
+        .as_ref()
+        .and_then(|handle| level_data_assets.get(handle))
+    else {
+        return;
+    };
+
+    let (direction, intensity) = sun_at(level_data, day_clock.0, *day_night_state.get());
+
+    sun_direction_res.0 = direction;
+    sun_intensity.0 = intensity;
+
+    if level_data.day_night_cycle == DayNightCycleData::Timed {
+        let day_night = timed_day_night(day_clock.0, level_data.day_length);
+
+        if day_night != *day_night_state.get() {
+            set_day_night.send(SetDayNightEvent(day_night));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels::data::LocationData;
+
+    use super::*;
+
+    fn timed_level(day_length: f32) -> LevelData {
+        LevelData {
+            day_length,
+            day_night_cycle: DayNightCycleData::Timed,
+            ..LevelData::new(1, 1, LocationData::new(0, 0), vec![])
+        }
+    }
+
+    #[test]
+    fn short_day_has_a_shorter_twilight() {
+        let level_data = timed_level(4.);
+
+        let (_, sunrise) = sun_at(&level_data, 0., DayNightState::Day);
+        let (_, end_of_night) = sun_at(&level_data, 8. - 0.001, DayNightState::Night);
+        let (_, midnight) = sun_at(&level_data, 6., DayNightState::Night);
+
+        assert_eq!(sunrise, 1.);
+        assert!(end_of_night > 0.99, "dawn ends at {}", end_of_night);
+        assert_eq!(midnight, 0.);
+    }
+}
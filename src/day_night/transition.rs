@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::day_night::sun::SunIntensity;
+use crate::debug::DebugUpdateSet;
+
+/// Seconds a switch between day and night takes to fade the sky, the shadows and the music
+pub const FADE_TIME: f32 = 4.;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayNightTransition>().add_systems(
+            Update,
+            (
+                advance_transition,
+                debug_cycle_transition_easing.in_set(DebugUpdateSet),
+            ),
+        );
+    }
+}
+
+/// Shape of the fade between day and night
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TransitionEasing {
+    Linear,
+    /// Starts and ends slowly
+    #[default]
+    SmoothStep,
+    /// Starts and ends slowly, following half a cosine wave
+    Sine,
+}
+
+impl TransitionEasing {
+    fn next(&self) -> Self {
+        match self {
+            Self::Linear => Self::SmoothStep,
+            Self::SmoothStep => Self::Sine,
+            Self::Sine => Self::Linear,
+        }
+    }
+
+    fn ease(&self, progress: f32) -> f32 {
+        match self {
+            Self::Linear => progress,
+            Self::SmoothStep => progress * progress * (3. - 2. * progress),
+            Self::Sine => (1. - (progress * PI).cos()) / 2.,
+        }
+    }
+}
+
+/// How far the fade between night and day has got. Everything that looks or sounds different at night reads it, so
+/// the sky, the shadows and the music all change together
+#[derive(Resource)]
+pub struct DayNightTransition {
+    /// From 0 at night to 1 during the day, moving towards the sun intensity no faster than [`FADE_TIME`] allows
+    progress: f32,
+    pub easing: TransitionEasing,
+}
+
+impl Default for DayNightTransition {
+    fn default() -> Self {
+        Self {
+            progress: 1.,
+            easing: TransitionEasing::default(),
+        }
+    }
+}
+
+impl DayNightTransition {
+    /// From 0 at night to 1 during the day, eased
+    pub fn daylight(&self) -> f32 {
+        self.easing.ease(self.progress)
+    }
+}
+
+/// Levels with a timed cycle fade with their dusk and dawn, which is never faster than the fade time, and the others
+/// fade over the fade time whenever the run switches between day and night
+fn advance_transition(
+    mut transition: ResMut<DayNightTransition>,
+    sun_intensity: Res<SunIntensity>,
+    time: Res<Time>,
+) {
+    let max_step = time.delta_seconds() / FADE_TIME;
+    let step = (sun_intensity.0 - transition.progress).clamp(-max_step, max_step);
+
+    if step != 0. {
+        transition.progress += step;
+    }
+}
+
+fn debug_cycle_transition_easing(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut transition: ResMut<DayNightTransition>,
+) {
+    if keys.just_pressed(KeyCode::KeyY) {
+        transition.easing = transition.easing.next();
+
+        println!("transition easing: {:?}", transition.easing);
+    }
+}
@@ -5,10 +5,13 @@ use std::io::Write;
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
+use crate::day_night::DayNightState;
 use crate::debug::DebugUpdateSet;
-use crate::levels::{CurrentLevel, MainMap, OverlayMap};
-use crate::levels::data::{LevelData, LocationData, OverlayData, TileData, TileTypeData};
+use crate::levels::{HALF_TILE_SIZE, MainMap, OverlayMap};
+use crate::levels::data::{LevelData, LocationData, TileData};
+use crate::levels::format::LevelFormat;
 use crate::levels::level_loader::LevelDataHandleRes;
+use crate::levels::registry::{OverlayDefinition, TileDefinition, TileRegistry};
 use crate::math::world_pos_to_tile_pos;
 
 pub struct DebugEditorPlugin;
@@ -16,7 +19,12 @@ pub struct DebugEditorPlugin;
 impl Plugin for DebugEditorPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<EditorState>()
-            .configure_sets(Update, EditorUpdateSet.run_if(in_state(EditorState::On)))
+            .configure_sets(
+                Update,
+                EditorUpdateSet
+                    .run_if(in_state(EditorState::On))
+                    .run_if(resource_exists::<TileRegistry>),
+            )
             .init_resource::<CurrentEditorTile>()
             .init_resource::<MousePosition>()
             .add_systems(
@@ -45,15 +53,25 @@ enum EditorState {
     On,
 }
 
-#[derive(Resource, Debug)]
-enum CurrentEditorTile {
-    Base(TileTypeData),
-    Overlay(OverlayData),
+/// Index into the tiles of the registry, followed by its overlays
+#[derive(Resource, Default, Debug)]
+struct CurrentEditorTile(usize);
+
+enum EditorTile<'a> {
+    Base(&'a TileDefinition),
+    Overlay(&'a OverlayDefinition),
 }
 
-impl Default for CurrentEditorTile {
-    fn default() -> Self {
-        Self::Base(TileTypeData::Dirt)
+impl CurrentEditorTile {
+    /// None if the registry was reloaded with fewer tiles since the tile was picked
+    fn get<'a>(&self, tile_registry: &'a TileRegistry) -> Option<EditorTile<'a>> {
+        match tile_registry.tiles.get(self.0) {
+            Some(tile) => Some(EditorTile::Base(tile)),
+            None => tile_registry
+                .overlays
+                .get(self.0 - tile_registry.tiles.len())
+                .map(EditorTile::Overlay),
+        }
     }
 }
 
@@ -80,23 +98,18 @@ fn toggle_editor_mode(
 fn change_editor_tile(
     keys: Res<ButtonInput<KeyCode>>,
     mut current_tile: ResMut<CurrentEditorTile>,
+    tile_registry: Res<TileRegistry>,
 ) {
     if keys.just_pressed(KeyCode::KeyN) {
-        let switch_to = match &*current_tile {
-            CurrentEditorTile::Base(tile) => match tile {
-                TileTypeData::Dirt => CurrentEditorTile::Base(TileTypeData::Stone),
-                TileTypeData::Stone => CurrentEditorTile::Base(TileTypeData::Water),
-                TileTypeData::Water => CurrentEditorTile::Overlay(OverlayData::Grass),
-            },
-
-            CurrentEditorTile::Overlay(overlay) => match overlay {
-                OverlayData::Grass => CurrentEditorTile::Base(TileTypeData::Dirt),
-            },
-        };
+        let tile_count = tile_registry.tiles.len() + tile_registry.overlays.len();
 
-        *current_tile = switch_to;
+        current_tile.0 = (current_tile.0 + 1) % tile_count.max(1);
 
-        println!("Current Tile: {:?}", &*current_tile);
+        match current_tile.get(&tile_registry) {
+            Some(EditorTile::Base(tile)) => println!("Current Tile: {}", tile.name),
+            Some(EditorTile::Overlay(overlay)) => println!("Current Overlay: {}", overlay.name),
+            None => println!("Tile registry is empty"),
+        }
     }
 }
 
@@ -130,14 +143,25 @@ fn editor_click_tile(
     mouse_button: Res<ButtonInput<MouseButton>>,
     mouse_position: Res<MousePosition>,
     current_editor_tile: Res<CurrentEditorTile>,
+    tile_registry: Res<TileRegistry>,
 ) {
-    if mouse_button.just_pressed(MouseButton::Left) {
-        let tile_pos = world_pos_to_tile_pos(mouse_position.world_pos.extend(0.)).into();
+    let Ok((_, main_map_storage)) = main_map_query.get_single() else {
+        return;
+    };
 
-        match &*current_editor_tile {
-            CurrentEditorTile::Base(base) => {
+    let Some(tile_pos) = cursor_tile_pos(&mouse_position, &main_map_storage.size) else {
+        return;
+    };
+
+    let Some(editor_tile) = current_editor_tile.get(&tile_registry) else {
+        return;
+    };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        match editor_tile {
+            EditorTile::Base(base) => {
                 let (main_map_entity, mut main_map_storage) = main_map_query.single_mut();
-                let texture_index = base.texture_index();
+                let texture_index = base.atlas_index;
 
                 set_tile_map_tile(
                     commands.reborrow(),
@@ -148,9 +172,9 @@ fn editor_click_tile(
                     main_map_entity,
                 );
             }
-            CurrentEditorTile::Overlay(overlay) => {
+            EditorTile::Overlay(overlay) => {
                 let (overlay_map_entity, mut overlay_map_storage) = overlay_map_query.single_mut();
-                let texture_index = overlay.texture_index();
+                let texture_index = overlay.atlas_index;
 
                 set_tile_map_tile(
                     commands.reborrow(),
@@ -163,15 +187,13 @@ fn editor_click_tile(
             }
         }
     } else if mouse_button.just_pressed(MouseButton::Right) {
-        let tile_pos = world_pos_to_tile_pos(mouse_position.world_pos.extend(0.)).into();
-
-        match &*current_editor_tile {
-            CurrentEditorTile::Base(_) => {
+        match editor_tile {
+            EditorTile::Base(_) => {
                 let (_, mut main_map_storage) = main_map_query.single_mut();
 
                 clear_tile(commands.reborrow(), &mut main_map_storage, tile_pos);
             }
-            CurrentEditorTile::Overlay(_) => {
+            EditorTile::Overlay(_) => {
                 let (_, mut overlay_map_storage) = overlay_map_query.single_mut();
 
                 clear_tile(commands.reborrow(), &mut overlay_map_storage, tile_pos);
@@ -180,6 +202,21 @@ fn editor_click_tile(
     }
 }
 
+/// The tile under the cursor, or none when the cursor is outside of the level
+fn cursor_tile_pos(mouse_position: &MousePosition, map_size: &TilemapSize) -> Option<TilePos> {
+    // world positions left of or below the level would saturate to tile 0 instead of being outside of it
+    if (mouse_position.world_pos + HALF_TILE_SIZE)
+        .cmplt(Vec2::ZERO)
+        .any()
+    {
+        return None;
+    }
+
+    let tile_pos: TilePos = world_pos_to_tile_pos(mouse_position.world_pos.extend(0.)).into();
+
+    tile_pos.within_map_bounds(map_size).then_some(tile_pos)
+}
+
 fn set_tile_map_tile(
     mut commands: Commands,
     tile_storage: &mut TileStorage,
@@ -214,44 +251,61 @@ fn clear_tile(mut commands: Commands, map_storage: &mut TileStorage, tile_pos: T
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn save_current_tile_map(
     main_map_query: Query<&TileStorage, With<MainMap>>,
     overlay_map_query: Query<&TileStorage, With<OverlayMap>>,
     tile_query: Query<(&TilePos, &TileTextureIndex)>,
     level_data_handle: Res<LevelDataHandleRes>,
-    current_level: Res<CurrentLevel>,
     level_data_assets: Res<Assets<LevelData>>,
+    asset_server: Res<AssetServer>,
+    tile_registry: Res<TileRegistry>,
+    day_night_state: Res<State<DayNightState>>,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
     if keys.just_pressed(KeyCode::KeyK) {
+        // the night run shows the tiles as they are at night, saving them would replace the level's day tiles
+        if *day_night_state.get() == DayNightState::Night {
+            println!("Levels can only be saved during the day run");
+            return;
+        }
+
         let tile_storage = main_map_query.single();
         let overlay_map_storage = overlay_map_query.single();
 
-        let spawn_location = if let Some(handle) = level_data_handle.0.clone() {
-            let level_data = level_data_assets.get(handle.id()).unwrap();
-            level_data.spawn_location
-        } else {
-            LocationData::new(0, 0)
+        let Some(handle) = level_data_handle.0.clone() else {
+            println!("No level loaded, nothing to save");
+            return;
         };
 
         let mut level_data = LevelData {
-            spawn_location,
             tiles: vec![],
+            ..level_data_assets.get(handle.id()).unwrap().clone()
         };
 
         for tile_entity in tile_storage.iter().flatten() {
             let (tile_pos, texture_index) = tile_query.get(*tile_entity).unwrap();
 
-            let over = if let Some(overlay_entity) = overlay_map_storage.get(tile_pos) {
-                let (_, texture_index) = tile_query.get(overlay_entity).unwrap();
-
-                Some(OverlayData::from_texture_index(texture_index.0))
-            } else {
-                None
+            // only happens if the registry changed its atlas indices while the level was being edited
+            let Some(tile) = tile_registry.tile_by_atlas_index(texture_index.0) else {
+                println!(
+                    "Skipping tile at {}, {} with atlas index {} which is not in the tile registry",
+                    tile_pos.x, tile_pos.y, texture_index.0
+                );
+                continue;
             };
 
+            let over = overlay_map_storage
+                .get(tile_pos)
+                .and_then(|overlay_entity| {
+                    let (_, texture_index) = tile_query.get(overlay_entity).unwrap();
+
+                    tile_registry.overlay_by_atlas_index(texture_index.0)
+                })
+                .map(|overlay| overlay.name.clone());
+
             level_data.tiles.push(TileData {
-                tile_type: TileTypeData::from_texture_index(texture_index.0),
+                tile_type: tile.name.clone(),
                 off: LocationData {
                     x: tile_pos.x,
                     y: tile_pos.y,
@@ -260,13 +314,22 @@ fn save_current_tile_map(
             });
         }
 
-        let json_str = serde_json::to_string(&level_data).unwrap();
+        // save back to the file the level was loaded from, in the format it was loaded from
+        let asset_path = asset_server.get_path(handle.id()).unwrap();
+        let file_out_path = &format!("assets/{}", asset_path.path().display());
 
-        let file_out_path = &format!("assets/level/level{}.lvl.json", current_level.0);
+        let format = LevelFormat::from_path(asset_path.path()).unwrap_or(LevelFormat::Json);
+        let level_str = match format.serialize(&level_data, &tile_registry) {
+            Ok(level_str) => level_str,
+            Err(err) => {
+                println!("Could not save {}: {}", file_out_path, err);
+                return;
+            }
+        };
 
         let mut file = File::create(file_out_path).unwrap();
 
-        file.write_all(json_str.as_bytes())
+        file.write_all(level_str.as_bytes())
             .expect("Unable to write to file");
 
         println!("Wrote data to file at {}", file_out_path);
@@ -45,6 +45,7 @@ fn toggle_debug_state(
                 println!("Press 'e' to toggle editor");
                 println!("Press 'o/p' to go to previous/next level");
                 println!("Press 't' to toggle tile map visibility");
+                println!("Press 'y' to cycle the day and night transition easing");
             } else {
                 next_debug_state.set(DebugState::Off);
                 println!("Debug Mode OFF");
@@ -0,0 +1,323 @@
+use std::io;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+use crate::levels::data::{LevelData, LocationData, OverlayData, TileData, TileTypeData};
+use crate::levels::registry::{read_tile_registry, TileRegistry, TileRegistryAssetLoaderError};
+use crate::levels::validation::{InvalidLevelError, validate_level};
+
+const EMPTY_GLYPH: char = '.';
+const SPAWN_GLYPH: char = 'S';
+const SNOW_GLYPH: char = 's';
+/// Glyphs that are not tiles, the tile registry can not give them to any of its tiles or overlays
+pub const RESERVED_GLYPHS: [char; 4] = [EMPTY_GLYPH, ' ', SPAWN_GLYPH, SNOW_GLYPH];
+
+/// Loads levels drawn as plain text, one character per tile with the top row of the level first. Tiles and overlays
+/// are drawn with the `ascii_glyph` the tile registry gives them, `S` is the spawn location, `s` snow to collect, and
+/// `.` or a space an empty tile. The exit, kill plane, clouds, forgiving sun and day and night settings can not be
+/// drawn, so they are always the defaults. The level is as wide as its longest row and as high as its number of rows
+#[derive(Default)]
+pub struct LevelASCIIAssetLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelASCIIAssetLoaderError {
+    #[error("Could not read file: {0}")]
+    IO(#[from] io::Error),
+    #[error("Could not load tile registry: {0}")]
+    TileRegistry(#[from] TileRegistryAssetLoaderError),
+    #[error("Level data was invalid: {0}")]
+    InvalidLevel(#[from] InvalidLevelError),
+    #[error("Unknown tile '{0}' at: {1}")]
+    UnknownGlyph(char, LocationData),
+    #[error("Level has no spawn location '{}'", SPAWN_GLYPH)]
+    MissingSpawn,
+    #[error("Level has more than one spawn location, the second one is at: {0}")]
+    MultipleSpawns(LocationData),
+    #[error("{0} with {1:?} at {2} has no ascii tile")]
+    UnrepresentableTile(TileTypeData, Option<OverlayData>, LocationData),
+    #[error("Tile at {0} is outside of the {width}x{height} level", width = .1.x, height = .1.y)]
+    TileOutOfBounds(LocationData, UVec2),
+    #[error("More than one of a tile, snow and the spawn location are at {0}, which ascii can only draw one of")]
+    OverlappingGlyphs(LocationData),
+    #[error("Levels with their own exit, kill plane, clouds, forgiving sun or day and night settings can not be written as ascii")]
+    UnrepresentableSettings,
+}
+
+impl AssetLoader for LevelASCIIAssetLoader {
+    type Asset = LevelData;
+    type Settings = ();
+    type Error = LevelASCIIAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelData, Self::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await?;
+
+        let tile_registry = read_tile_registry(load_context).await?;
+
+        let level_data = deserialize_level_ascii(&text, &tile_registry)?;
+
+        validate_level(&level_data, &tile_registry)?;
+
+        Ok(level_data)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lvl.txt"]
+    }
+}
+
+/// Parses an ascii level without validating it
+pub fn deserialize_level_ascii(
+    text: &str,
+    tile_registry: &TileRegistry,
+) -> Result<LevelData, LevelASCIIAssetLoaderError> {
+    // editors like to add blank lines at the end of a file, empty rows at the bottom of a level need to be drawn as `.`
+    let rows = text
+        .lines()
+        .rev()
+        .skip_while(|row| row.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    let width = rows
+        .iter()
+        .map(|row| row.trim_end().chars().count() as u32)
+        .max()
+        .unwrap_or(0);
+
+    let mut spawn_location = None;
+    let mut tiles = vec![];
+    let mut snow = vec![];
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, glyph) in row.chars().enumerate() {
+            let loc = LocationData::new(x as u32, y as u32);
+
+            let (tile_type, over) = match glyph {
+                EMPTY_GLYPH | ' ' => continue,
+                SPAWN_GLYPH => {
+                    if spawn_location.replace(loc).is_some() {
+                        return Err(LevelASCIIAssetLoaderError::MultipleSpawns(loc));
+                    }
+
+                    continue;
+                }
+                SNOW_GLYPH => {
+                    snow.push(loc);
+                    continue;
+                }
+                _ => tile_from_glyph(glyph, tile_registry)
+                    .ok_or(LevelASCIIAssetLoaderError::UnknownGlyph(glyph, loc))?,
+            };
+
+            tiles.push(TileData {
+                tile_type,
+                off: loc,
+                over,
+            });
+        }
+    }
+
+    let spawn_location = spawn_location.ok_or(LevelASCIIAssetLoaderError::MissingSpawn)?;
+
+    Ok(LevelData {
+        snow,
+        ..LevelData::new(width, rows.len() as u32, spawn_location, tiles)
+    })
+}
+
+/// Draws a level as ascii, failing for anything the ascii format can not hold rather than dropping it
+pub fn serialize_level_ascii(
+    level_data: &LevelData,
+    tile_registry: &TileRegistry,
+) -> Result<String, LevelASCIIAssetLoaderError> {
+    if !level_data.has_default_settings() {
+        return Err(LevelASCIIAssetLoaderError::UnrepresentableSettings);
+    }
+
+    let mut grid = HashMap::new();
+
+    for tile in &level_data.tiles {
+        let glyph = glyph_of(tile, tile_registry).ok_or_else(|| {
+            LevelASCIIAssetLoaderError::UnrepresentableTile(
+                tile.tile_type.clone(),
+                tile.over.clone(),
+                tile.off,
+            )
+        })?;
+
+        if !level_data.is_in_bounds(tile.off) {
+            return Err(LevelASCIIAssetLoaderError::TileOutOfBounds(
+                tile.off,
+                level_data.size(),
+            ));
+        }
+
+        if grid.insert(tile.off, glyph).is_some() {
+            return Err(LevelASCIIAssetLoaderError::OverlappingGlyphs(tile.off));
+        }
+    }
+
+    let glyphs = level_data
+        .snow
+        .iter()
+        .map(|snow| (*snow, SNOW_GLYPH))
+        .chain([(level_data.spawn_location, SPAWN_GLYPH)]);
+
+    for (loc, glyph) in glyphs {
+        if grid.insert(loc, glyph).is_some() {
+            return Err(LevelASCIIAssetLoaderError::OverlappingGlyphs(loc));
+        }
+    }
+
+    let mut text = String::new();
+
+    for y in (0..level_data.height).rev() {
+        for x in 0..level_data.width {
+            text.push(*grid.get(&LocationData::new(x, y)).unwrap_or(&EMPTY_GLYPH));
+        }
+
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+/// The tile a glyph stands for, with the overlay on top of it if the glyph is an overlay's
+fn tile_from_glyph(
+    glyph: char,
+    tile_registry: &TileRegistry,
+) -> Option<(TileTypeData, Option<OverlayData>)> {
+    if let Some(tile) = tile_registry.tile_by_ascii_glyph(glyph) {
+        return Some((tile.name.clone(), None));
+    }
+
+    tile_registry.overlays.iter().find_map(|overlay| {
+        overlay
+            .ascii_glyph
+            .as_ref()
+            .filter(|overlay_glyph| overlay_glyph.glyph == glyph)
+            .map(|overlay_glyph| (overlay_glyph.on.clone(), Some(overlay.name.clone())))
+    })
+}
+
+/// Overlays can only be drawn on top of the one tile their glyph puts them on
+fn glyph_of(tile: &TileData, tile_registry: &TileRegistry) -> Option<char> {
+    match &tile.over {
+        None => tile_registry
+            .tile(&tile.tile_type)
+            .and_then(|definition| definition.ascii_glyph),
+        Some(over) => tile_registry
+            .overlay(over)
+            .and_then(|overlay| overlay.ascii_glyph.as_ref())
+            .filter(|overlay_glyph| overlay_glyph.on == tile.tile_type)
+            .map(|overlay_glyph| overlay_glyph.glyph),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels::test_support::{assert_same_level, shipped_level, tile_registry};
+
+    use super::*;
+
+    /// A shipped level with only tiles that have a glyph and default settings
+    const SHIPPED_LEVEL_PATH: &str = "level/level0.lvl.json";
+
+    #[test]
+    fn shipped_level_survives_round_trip() {
+        let tile_registry = tile_registry();
+        let level_data = LevelData {
+            snow: vec![LocationData::new(1, 10)],
+            ..shipped_level(SHIPPED_LEVEL_PATH)
+        };
+
+        let text = serialize_level_ascii(&level_data, &tile_registry).unwrap();
+        let parsed = deserialize_level_ascii(&text, &tile_registry).unwrap();
+
+        assert_same_level(&parsed, &level_data);
+    }
+
+    #[test]
+    fn unknown_glyph_is_rejected() {
+        let result = deserialize_level_ascii("S?\n##\n", &tile_registry());
+
+        assert!(matches!(
+            result,
+            Err(LevelASCIIAssetLoaderError::UnknownGlyph('?', loc)) if loc == LocationData::new(1, 1)
+        ));
+    }
+
+    #[test]
+    fn level_without_spawn_is_rejected() {
+        let result = deserialize_level_ascii("..\n##\n", &tile_registry());
+
+        assert!(matches!(
+            result,
+            Err(LevelASCIIAssetLoaderError::MissingSpawn)
+        ));
+    }
+
+    #[test]
+    fn custom_settings_can_not_be_written() {
+        let level_data = LevelData {
+            forgiving_sun: true,
+            ..shipped_level(SHIPPED_LEVEL_PATH)
+        };
+
+        assert!(matches!(
+            serialize_level_ascii(&level_data, &tile_registry()),
+            Err(LevelASCIIAssetLoaderError::UnrepresentableSettings)
+        ));
+    }
+
+    #[test]
+    fn tile_under_spawn_is_rejected() {
+        let mut level_data = shipped_level(SHIPPED_LEVEL_PATH);
+        level_data.tiles.push(TileData {
+            tile_type: "Stone".into(),
+            off: level_data.spawn_location,
+            over: None,
+        });
+
+        assert!(matches!(
+            serialize_level_ascii(&level_data, &tile_registry()),
+            Err(LevelASCIIAssetLoaderError::OverlappingGlyphs(loc)) if loc == level_data.spawn_location
+        ));
+    }
+
+    #[test]
+    fn snow_on_tile_is_rejected() {
+        let mut level_data = shipped_level(SHIPPED_LEVEL_PATH);
+        let off = level_data.tiles[0].off;
+        level_data.snow.push(off);
+
+        assert!(matches!(
+            serialize_level_ascii(&level_data, &tile_registry()),
+            Err(LevelASCIIAssetLoaderError::OverlappingGlyphs(loc)) if loc == off
+        ));
+    }
+
+    #[test]
+    fn tile_out_of_bounds_is_rejected() {
+        let mut level_data = shipped_level(SHIPPED_LEVEL_PATH);
+        let off = LocationData::new(level_data.width, 0);
+        level_data.tiles.push(TileData {
+            tile_type: "Stone".into(),
+            off,
+            over: None,
+        });
+
+        assert!(matches!(
+            serialize_level_ascii(&level_data, &tile_registry()),
+            Err(LevelASCIIAssetLoaderError::TileOutOfBounds(loc, _)) if loc == off
+        ));
+    }
+}
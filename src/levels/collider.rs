@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::levels::data::LocationData;
+use crate::levels::TILE_SIZE;
+use crate::math::tile_pos_to_world_pos_2d;
+
+/// A rectangle of tiles, with both corners included
+#[derive(Debug, Copy, Clone)]
+pub struct TileRect {
+    pub min: UVec2,
+    pub max: UVec2,
+}
+
+impl TileRect {
+    /// Center of the rectangle relative to the tile map
+    pub fn center(&self) -> Vec2 {
+        (tile_pos_to_world_pos_2d(self.min) + tile_pos_to_world_pos_2d(self.max)) / 2.
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        (self.max - self.min + UVec2::ONE).as_vec2() * TILE_SIZE / 2.
+    }
+}
+
+/// Covers the tiles with as few rectangles as the greedy approach finds, growing each one right as far as it can and
+/// then up for as long as the whole row is there. Neighbouring rectangles share edges but never overlap
+pub fn merge_tiles(tiles: &HashSet<LocationData>) -> Vec<TileRect> {
+    let mut sorted = tiles.iter().copied().collect::<Vec<_>>();
+    sorted.sort_by_key(|loc| (loc.y, loc.x));
+
+    let mut merged = HashSet::new();
+    let mut rects = vec![];
+
+    for start in sorted {
+        if merged.contains(&start) {
+            continue;
+        }
+
+        let is_free = |x, y| {
+            let loc = LocationData::new(x, y);
+
+            tiles.contains(&loc) && !merged.contains(&loc)
+        };
+
+        let mut max_x = start.x;
+
+        while is_free(max_x + 1, start.y) {
+            max_x += 1;
+        }
+
+        let mut max_y = start.y;
+
+        while (start.x..=max_x).all(|x| is_free(x, max_y + 1)) {
+            max_y += 1;
+        }
+
+        for x in start.x..=max_x {
+            for y in start.y..=max_y {
+                merged.insert(LocationData::new(x, y));
+            }
+        }
+
+        rects.push(TileRect {
+            min: start.into(),
+            max: UVec2::new(max_x, max_y),
+        });
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels::test_support::{shipped_level, tile_registry};
+
+    use super::*;
+
+    const SHIPPED_LEVEL_PATH: &str = "level/level1.lvl.json";
+
+    fn tile_set(locs: &[(u32, u32)]) -> HashSet<LocationData> {
+        locs.iter().map(|&(x, y)| LocationData::new(x, y)).collect()
+    }
+
+    /// Every tile has to be covered by exactly one rectangle, and the rectangles may not reach past the tiles
+    fn assert_covers_exactly_once(tiles: &HashSet<LocationData>) {
+        let mut covered = HashSet::new();
+
+        for rect in merge_tiles(tiles) {
+            assert!(
+                rect.min.x <= rect.max.x && rect.min.y <= rect.max.y,
+                "{rect:?} is inside out"
+            );
+
+            for x in rect.min.x..=rect.max.x {
+                for y in rect.min.y..=rect.max.y {
+                    let loc = LocationData::new(x, y);
+
+                    assert!(
+                        tiles.contains(&loc),
+                        "{rect:?} covers {loc:?}, which is not a tile"
+                    );
+                    assert!(covered.insert(loc), "{loc:?} is covered more than once");
+                }
+            }
+        }
+
+        assert_eq!(&covered, tiles);
+    }
+
+    #[test]
+    fn no_tiles_need_no_rectangles() {
+        assert!(merge_tiles(&HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn full_block_is_one_rectangle() {
+        let tiles = tile_set(&[(2, 3), (3, 3), (4, 3), (2, 4), (3, 4), (4, 4)]);
+
+        let rects = merge_tiles(&tiles);
+
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].min, UVec2::new(2, 3));
+        assert_eq!(rects[0].max, UVec2::new(4, 4));
+        assert_covers_exactly_once(&tiles);
+    }
+
+    #[test]
+    fn l_shape_is_covered_exactly_once() {
+        assert_covers_exactly_once(&tile_set(&[(0, 0), (1, 0), (2, 0), (0, 1), (0, 2), (0, 3)]));
+        assert_covers_exactly_once(&tile_set(&[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]));
+    }
+
+    #[test]
+    fn tiles_with_gaps_are_covered_exactly_once() {
+        assert_covers_exactly_once(&tile_set(&[
+            (0, 0),
+            (2, 0),
+            (3, 0),
+            (0, 1),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (1, 2),
+            (3, 2),
+            (5, 5),
+        ]));
+    }
+
+    #[test]
+    fn shipped_level_solid_tiles_are_covered_exactly_once() {
+        let tile_registry = tile_registry();
+        let level_data = shipped_level(SHIPPED_LEVEL_PATH);
+
+        let tiles = level_data
+            .tiles
+            .iter()
+            .filter(|tile| tile_registry.is_solid(&tile.tile_type))
+            .map(|tile| tile.off)
+            .collect::<HashSet<_>>();
+
+        assert!(!tiles.is_empty());
+        assert_covers_exactly_once(&tiles);
+    }
+}
@@ -2,11 +2,26 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 
 use bevy::asset::Asset;
-use bevy::math::UVec2;
+use bevy::math::{IVec2, UVec2, Vec2};
 use bevy::prelude::TypePath;
 use bevy_ecs_tilemap::tiles::TilePos;
 use serde::{de, de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::levels::{HALF_TILE_SIZE, TILE_SIZE};
+use crate::levels::migration::CURRENT_LEVEL_VERSION;
+use crate::math::tile_pos_to_world_pos_2d;
+
+/// World x coordinate the player has to pass to finish levels that do not set their own exit
+const DEFAULT_EXIT_X: f32 = 320.;
+/// World y coordinate below which the player dies in levels that do not set their own kill plane
+const DEFAULT_KILL_PLANE: f32 = -20.;
+/// Seconds the sun takes to cross the sky in levels that do not set their own day length
+const DEFAULT_DAY_LENGTH: f32 = 60.;
+/// Tiles the snowman can see around it at night in levels that do not set their own sight radius
+const DEFAULT_SIGHT_RADIUS: f32 = 4.;
+/// Size in tiles of clouds that do not set their own, the size of the cloud sprite
+const DEFAULT_CLOUD_SIZE: Vec2 = Vec2::new(2., 1.);
+
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub struct LocationData {
     pub x: u32,
@@ -79,68 +94,287 @@ impl From<LocationData> for UVec2 {
     }
 }
 
-#[derive(Deserialize, Serialize, Hash, Debug, Copy, Clone)]
-pub enum TileTypeData {
-    Dirt,
-    Stone,
-    Water,
+/// Name of a tile in the [`TileRegistry`](crate::levels::registry::TileRegistry)
+#[derive(Deserialize, Serialize, Hash, Debug, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct TileTypeData(pub String);
+
+impl From<&str> for TileTypeData {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl Display for TileTypeData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Name of an overlay in the [`TileRegistry`](crate::levels::registry::TileRegistry)
+#[derive(Deserialize, Serialize, Hash, Debug, Clone, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct OverlayData(pub String);
+
+impl From<&str> for OverlayData {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl Display for OverlayData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Deserialize, Serialize, Hash, Debug, Clone, PartialEq, Eq)]
+pub struct TileData {
+    pub tile_type: TileTypeData,
+    pub off: LocationData,
+    pub over: Option<OverlayData>,
 }
 
-impl TileTypeData {
-    pub fn texture_index(&self) -> u32 {
+/// An area the player finishes the level by entering
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub enum ExitRegionData {
+    /// Everything right of the world x coordinate
+    RightOf(f32),
+    /// Everything left of the world x coordinate
+    LeftOf(f32),
+    /// Everything above the world y coordinate
+    Above(f32),
+    /// A rectangle of tiles, with both corners included
+    Tiles {
+        from: LocationData,
+        to: LocationData,
+    },
+}
+
+impl ExitRegionData {
+    pub fn contains(&self, world_pos: Vec2) -> bool {
         match self {
-            Self::Dirt => 0,
-            Self::Stone => 1,
-            Self::Water => 3,
+            Self::RightOf(x) => world_pos.x > *x,
+            Self::LeftOf(x) => world_pos.x < *x,
+            Self::Above(y) => world_pos.y > *y,
+            Self::Tiles { from, to } => {
+                let min = tile_pos_to_world_pos_2d((*from).into()) - HALF_TILE_SIZE;
+                let max = tile_pos_to_world_pos_2d((*to).into()) + HALF_TILE_SIZE;
+
+                world_pos.cmpge(min).all() && world_pos.cmplt(max).all()
+            }
         }
     }
 
-    pub fn from_texture_index(texture_index: u32) -> Self {
-        match texture_index {
-            0 => Self::Dirt,
-            1 => Self::Stone,
-            3 => Self::Water,
-            _ => unreachable!(),
+    /// Whether any part of the tile is inside the region, the tile can be outside of the level to reach exits placed
+    /// past its edges
+    pub fn overlaps_tile(&self, tile_pos: IVec2) -> bool {
+        let center = tile_pos.as_vec2() * TILE_SIZE;
+
+        match self {
+            Self::RightOf(x) => center.x + HALF_TILE_SIZE > *x,
+            Self::LeftOf(x) => center.x - HALF_TILE_SIZE < *x,
+            Self::Above(y) => center.y + HALF_TILE_SIZE > *y,
+            Self::Tiles { .. } => self.contains(center),
         }
     }
 
-    pub fn is_hazard(&self) -> bool {
+    pub fn is_valid(&self) -> bool {
         match self {
-            Self::Water => true,
-            _ => false,
+            Self::Tiles { from, to } => from.x <= to.x && from.y <= to.y,
+            _ => true,
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Hash, Debug)]
-pub enum OverlayData {
-    Grass,
+/// A cloud drifting along a path and back again, shading everything below it from the sun
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CloudData {
+    /// Tiles the cloud drifts between in order, starting at the first at the start of every run
+    pub path: Vec<LocationData>,
+    /// Tiles per second
+    pub speed: f32,
+    /// Size in tiles
+    #[serde(default = "CloudData::default_width")]
+    pub width: f32,
+    #[serde(default = "CloudData::default_height")]
+    pub height: f32,
 }
 
-impl OverlayData {
-    pub fn texture_index(&self) -> u32 {
-        match self {
-            Self::Grass => 2,
-        }
+impl CloudData {
+    pub fn default_width() -> f32 {
+        DEFAULT_CLOUD_SIZE.x
     }
 
-    pub fn from_texture_index(texture_index: u32) -> Self {
-        match texture_index {
-            2 => Self::Grass,
-            _ => unreachable!(),
+    pub fn default_height() -> f32 {
+        DEFAULT_CLOUD_SIZE.y
+    }
+
+    /// Size in world units
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height) * TILE_SIZE
+    }
+
+    /// World position of the center of the cloud the given number of seconds into a run
+    pub fn position_at(&self, elapsed: f32) -> Vec2 {
+        let points = self
+            .path
+            .iter()
+            .map(|loc| tile_pos_to_world_pos_2d((*loc).into()))
+            .collect::<Vec<_>>();
+
+        let length: f32 = points
+            .windows(2)
+            .map(|segment| segment[0].distance(segment[1]))
+            .sum();
+
+        if length == 0. {
+            return points.first().copied().unwrap_or_default();
+        }
+
+        // the cloud goes there and back again, so it covers the path twice per round trip
+        let travelled = (elapsed * self.speed * TILE_SIZE).rem_euclid(length * 2.);
+        let mut remaining = if travelled > length {
+            length * 2. - travelled
+        } else {
+            travelled
+        };
+
+        for segment in points.windows(2) {
+            let segment_length = segment[0].distance(segment[1]);
+
+            if segment_length > 0. && remaining <= segment_length {
+                return segment[0].lerp(segment[1], remaining / segment_length);
+            }
+
+            remaining -= segment_length;
         }
+
+        points[points.len() - 1]
+    }
+
+    pub fn contains(&self, elapsed: f32, world_pos: Vec2) -> bool {
+        let half_size = self.size() / 2.;
+        let center = self.position_at(elapsed);
+
+        world_pos.cmpge(center - half_size).all() && world_pos.cmplt(center + half_size).all()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.path.is_empty() && self.speed >= 0. && self.width > 0. && self.height > 0.
     }
 }
 
-#[derive(Deserialize, Serialize, Hash, Debug)]
-pub struct TileData {
-    pub tile_type: TileTypeData,
-    pub off: LocationData,
-    pub over: Option<OverlayData>,
+/// How a level switches between day and night
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DayNightCycleData {
+    /// The level is played once during the day and once at night, switching when the exit is reached
+    #[default]
+    PerRun,
+    /// A clock that keeps running while the level is played, with a night as long as the day and a dusk and dawn in
+    /// between. Reaching the exit once finishes the level
+    Timed,
 }
 
-#[derive(Deserialize, Serialize, Asset, TypePath, Hash, Debug)]
+#[derive(Deserialize, Serialize, Asset, TypePath, Debug, Clone)]
 pub struct LevelData {
+    /// Format version of the level, older versions are migrated when loaded
+    pub version: u32,
+    /// Size of the level in tiles, every tile and the spawn location must be inside it
+    pub width: u32,
+    pub height: u32,
     pub spawn_location: LocationData,
     pub tiles: Vec<TileData>,
+    /// Entering any of these finishes the level, empty for screens such as the ending which can not be finished
+    #[serde(default = "LevelData::default_exit")]
+    pub exit: Vec<ExitRegionData>,
+    /// World y coordinate below which the player dies
+    #[serde(default = "LevelData::default_kill_plane")]
+    pub kill_plane: f32,
+    /// Seconds the sun takes to cross the sky, after which it stays where it set until the next run, or until it rises
+    /// again in levels with a timed cycle
+    #[serde(default = "LevelData::default_day_length")]
+    pub day_length: f32,
+    #[serde(default)]
+    pub day_night_cycle: DayNightCycleData,
+    /// Radius in tiles of the light around the snowman at night
+    #[serde(default = "LevelData::default_sight_radius")]
+    pub sight_radius: f32,
+    #[serde(default)]
+    pub clouds: Vec<CloudData>,
+    /// Locations of the snow the snowman can collect to undo a melt stage, all of it comes back whenever it respawns
+    #[serde(default)]
+    pub snow: Vec<LocationData>,
+    /// Whether the sun stops melting the snowman once it is mostly melted, instead of melting it into a puddle
+    #[serde(default)]
+    pub forgiving_sun: bool,
+}
+
+impl LevelData {
+    /// A level of the current version with every optional setting left at its default
+    pub fn new(
+        width: u32,
+        height: u32,
+        spawn_location: LocationData,
+        tiles: Vec<TileData>,
+    ) -> Self {
+        Self {
+            version: CURRENT_LEVEL_VERSION,
+            width,
+            height,
+            spawn_location,
+            tiles,
+            exit: Self::default_exit(),
+            kill_plane: Self::default_kill_plane(),
+            day_length: Self::default_day_length(),
+            day_night_cycle: DayNightCycleData::default(),
+            sight_radius: Self::default_sight_radius(),
+            clouds: vec![],
+            snow: vec![],
+            forgiving_sun: false,
+        }
+    }
+
+    pub fn default_exit() -> Vec<ExitRegionData> {
+        vec![ExitRegionData::RightOf(DEFAULT_EXIT_X)]
+    }
+
+    pub fn default_kill_plane() -> f32 {
+        DEFAULT_KILL_PLANE
+    }
+
+    pub fn default_day_length() -> f32 {
+        DEFAULT_DAY_LENGTH
+    }
+
+    pub fn default_sight_radius() -> f32 {
+        DEFAULT_SIGHT_RADIUS
+    }
+
+    /// Whether every optional setting is the one levels get when they do not set their own
+    pub fn has_default_settings(&self) -> bool {
+        self.exit == Self::default_exit()
+            && self.kill_plane == Self::default_kill_plane()
+            && self.day_length == Self::default_day_length()
+            && self.day_night_cycle == DayNightCycleData::default()
+            && self.sight_radius == Self::default_sight_radius()
+            && self.clouds.is_empty()
+            && !self.forgiving_sun
+    }
+
+    pub fn size(&self) -> UVec2 {
+        UVec2::new(self.width, self.height)
+    }
+
+    pub fn is_in_bounds(&self, loc: LocationData) -> bool {
+        loc.x < self.width && loc.y < self.height
+    }
+
+    pub fn is_exit(&self, world_pos: Vec2) -> bool {
+        self.exit.iter().any(|exit| exit.contains(world_pos))
+    }
+
+    pub fn is_below_kill_plane(&self, world_pos: Vec2) -> bool {
+        world_pos.y < self.kill_plane
+    }
 }
@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::levels::ascii::{deserialize_level_ascii, LevelASCIIAssetLoaderError, serialize_level_ascii};
+use crate::levels::data::LevelData;
+use crate::levels::level_loader::{deserialize_level_json, LevelJSONAssetLoaderError};
+use crate::levels::registry::{ASSET_DIRECTORY, TileRegistry};
+use crate::levels::rle::{deserialize_level_rle, LevelRLEAssetLoaderError, serialize_level_rle};
+use crate::levels::tiled::{
+    DEFAULT_TILED_TABLE_PATH, deserialize_level_tiled, LevelTiledAssetLoaderError, TiledTable,
+};
+
+/// Every file format a level can be loaded from, picked by the file extension the same way the asset loaders are
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LevelFormat {
+    Json,
+    Rle,
+    Ascii,
+    /// Maps made in Tiled, which can be imported but not written back
+    Tiled,
+}
+
+#[derive(Debug, Error)]
+pub enum LevelFormatError {
+    #[error("{0}")]
+    Json(#[from] LevelJSONAssetLoaderError),
+    #[error("{0}")]
+    Rle(#[from] LevelRLEAssetLoaderError),
+    #[error("{0}")]
+    Ascii(#[from] LevelASCIIAssetLoaderError),
+    #[error("{0}")]
+    Tiled(#[from] LevelTiledAssetLoaderError),
+    #[error("Levels can not be written as .{}, only imported", .0.extension())]
+    ImportOnly(LevelFormat),
+    #[error("Could not write json: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Level file is not valid utf-8")]
+    NotUtf8,
+}
+
+impl LevelFormat {
+    pub const ALL: [LevelFormat; 4] = [
+        LevelFormat::Json,
+        LevelFormat::Rle,
+        LevelFormat::Ascii,
+        LevelFormat::Tiled,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LevelFormat::Json => "lvl.json",
+            LevelFormat::Rle => "lvl.rle",
+            LevelFormat::Ascii => "lvl.txt",
+            LevelFormat::Tiled => "tmj",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<LevelFormat> {
+        let file_name = path.file_name()?.to_str()?;
+
+        LevelFormat::ALL
+            .into_iter()
+            .find(|format| file_name.ends_with(&format!(".{}", format.extension())))
+    }
+
+    /// Parses a level, migrating it from older versions, without validating it. Tiled maps use the default table,
+    /// read straight from the asset directory since this runs without the asset server. The tile registry holds the
+    /// codes and glyphs of the text formats
+    pub fn deserialize(
+        &self,
+        bytes: &[u8],
+        tile_registry: &TileRegistry,
+    ) -> Result<LevelData, LevelFormatError> {
+        match self {
+            LevelFormat::Json => Ok(deserialize_level_json(bytes)?),
+            LevelFormat::Rle => Ok(deserialize_level_rle(as_text(bytes)?, tile_registry)?),
+            LevelFormat::Ascii => Ok(deserialize_level_ascii(as_text(bytes)?, tile_registry)?),
+            LevelFormat::Tiled => {
+                let table_path = Path::new(ASSET_DIRECTORY).join(DEFAULT_TILED_TABLE_PATH);
+                let table_bytes = fs::read(table_path).map_err(LevelTiledAssetLoaderError::from)?;
+                let table: TiledTable = serde_json::from_slice(&table_bytes)
+                    .map_err(LevelTiledAssetLoaderError::from)?;
+
+                Ok(deserialize_level_tiled(bytes, &table)?)
+            }
+        }
+    }
+
+    pub fn serialize(
+        &self,
+        level_data: &LevelData,
+        tile_registry: &TileRegistry,
+    ) -> Result<String, LevelFormatError> {
+        match self {
+            LevelFormat::Json => Ok(serde_json::to_string(level_data)?),
+            LevelFormat::Rle => Ok(serialize_level_rle(level_data, tile_registry)?),
+            LevelFormat::Ascii => Ok(serialize_level_ascii(level_data, tile_registry)?),
+            LevelFormat::Tiled => Err(LevelFormatError::ImportOnly(*self)),
+        }
+    }
+}
+
+fn as_text(bytes: &[u8]) -> Result<&str, LevelFormatError> {
+    std::str::from_utf8(bytes).map_err(|_| LevelFormatError::NotUtf8)
+}
@@ -1,30 +1,83 @@
 use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
 
+use crate::player::Player;
 use crate::player::respawn::KillPlayerEvent;
 
 pub struct HazardPlugin;
 
 impl Plugin for HazardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, player_contact_hazard);
+        app.add_event::<PlayerHitHazardEvent>().add_systems(
+            Update,
+            (
+                player_contact_hazard,
+                kill_player_on_hazard.after(player_contact_hazard),
+            ),
+        );
     }
 }
 
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HazardKind {
+    Water,
+    Lava,
+}
+
+/// Sensor of a tile that hurts the player on contact
+#[derive(Component)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub tile_pos: TilePos,
+}
+
+#[derive(Event)]
+pub struct PlayerHitHazardEvent {
+    pub kind: HazardKind,
+    pub tile_pos: TilePos,
+}
+
+/// Only collisions between the player and a [`Hazard`] count, and the events are left for other systems to read
 fn player_contact_hazard(
     mut collision_event: EventReader<CollisionEvent>,
-    mut kill_player: EventWriter<KillPlayerEvent>,
+    player_query: Query<(), With<Player>>,
+    hazard_query: Query<&Hazard>,
+    mut player_hit_hazard: EventWriter<PlayerHitHazardEvent>,
 ) {
-    let collided = if let Some(collision) = collision_event.read().next() {
-        if let CollisionEvent::Started(_, _, _) = collision {
-            kill_player.send_default();
+    for collision in collision_event.read() {
+        let CollisionEvent::Started(entity_a, entity_b, _) = collision else {
+            continue;
+        };
+
+        let hazard_entity = if player_query.contains(*entity_a) {
+            *entity_b
+        } else if player_query.contains(*entity_b) {
+            *entity_a
+        } else {
+            continue;
+        };
+
+        if let Ok(hazard) = hazard_query.get(hazard_entity) {
+            player_hit_hazard.send(PlayerHitHazardEvent {
+                kind: hazard.kind,
+                tile_pos: hazard.tile_pos,
+            });
         }
-        true
-    } else {
-        false
-    };
+    }
+}
 
-    if collided {
-        collision_event.clear();
+fn kill_player_on_hazard(
+    mut player_hit_hazard: EventReader<PlayerHitHazardEvent>,
+    mut kill_player: EventWriter<KillPlayerEvent>,
+) {
+    // touching several hazards in the same frame still only kills the player once
+    if let Some(hit) = player_hit_hazard.read().last() {
+        println!(
+            "Player hit {:?} at {}, {}",
+            hit.kind, hit.tile_pos.x, hit.tile_pos.y
+        );
+        kill_player.send_default();
     }
 }
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::levels::TILE_SIZE;
+
+/// Tile that melts the player within its radius, whether or not the sun is out and whether or not the player is in
+/// the shade. Heat from several sources adds up
+#[derive(Component, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct HeatSource {
+    /// Distance in tiles from the center of the tile at which the heat stops
+    pub radius: f32,
+    /// Seconds of sunlight each second next to the source is worth, falling off evenly towards the radius
+    pub rate: f32,
+}
+
+impl HeatSource {
+    pub fn rate_at(&self, distance: f32) -> f32 {
+        let radius = self.radius * TILE_SIZE;
+
+        if distance >= radius {
+            return 0.;
+        }
+
+        self.rate * (1. - distance / radius)
+    }
+}
@@ -1,13 +1,18 @@
-use std::fmt::Formatter;
 use std::io;
 
 use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
 use bevy::prelude::*;
-use bevy::utils::HashSet;
 use thiserror::Error;
 
-use crate::levels::data::{LevelData, LocationData};
+use crate::levels::ascii::LevelASCIIAssetLoader;
+use crate::levels::data::LevelData;
 use crate::levels::LoadLevelEvent;
+use crate::levels::manifest::{LevelManifest, LevelManifestHandleRes};
+use crate::levels::migration::{LevelMigrationError, migrate_level};
+use crate::levels::rle::LevelRLEAssetLoader;
+use crate::levels::tiled::LevelTiledAssetLoader;
+use crate::levels::registry::{read_tile_registry, TileRegistryAssetLoaderError};
+use crate::levels::validation::{InvalidLevelError, validate_level};
 
 pub struct LevelLoaderPlugin;
 
@@ -16,6 +21,9 @@ impl Plugin for LevelLoaderPlugin {
         app.add_event::<LevelDataLoadedEvent>()
             .init_resource::<LevelDataHandleRes>()
             .init_asset_loader::<LevelJSONAssetLoader>()
+            .init_asset_loader::<LevelRLEAssetLoader>()
+            .init_asset_loader::<LevelASCIIAssetLoader>()
+            .init_asset_loader::<LevelTiledAssetLoader>()
             .init_asset::<LevelData>()
             .add_systems(Update, (load_level, set_loaded_level));
     }
@@ -24,42 +32,29 @@ impl Plugin for LevelLoaderPlugin {
 #[derive(Default)]
 struct LevelJSONAssetLoader;
 
-#[derive(Debug)]
-enum InvalidLevelErrorReason {
-    DuplicateTileLocation(LocationData),
-}
-
 #[derive(Debug, Error)]
-struct InvalidLevelError {
-    reason: InvalidLevelErrorReason,
-}
-
-impl InvalidLevelError {
-    fn new(reason: InvalidLevelErrorReason) -> Self {
-        Self { reason }
-    }
-}
-
-impl std::fmt::Display for InvalidLevelError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self.reason {
-            InvalidLevelErrorReason::DuplicateTileLocation(loc) => {
-                write!(f, "Duplicate tile location at: {}", loc)
-            }
-        }
-    }
-}
-
-#[derive(Debug, Error)]
-enum LevelJSONAssetLoaderError {
+pub enum LevelJSONAssetLoaderError {
     #[error("Could not parse json: {0}")]
     SerdeParse(#[from] serde_json::Error),
     #[error("Could not read file: {0}")]
     IO(#[from] io::Error),
+    #[error("Could not migrate level: {0}")]
+    Migration(#[from] LevelMigrationError),
+    #[error("Could not load tile registry: {0}")]
+    TileRegistry(#[from] TileRegistryAssetLoaderError),
     #[error("Level data was invalid: {0}")]
     InvalidLevel(#[from] InvalidLevelError),
 }
 
+/// Parses a level json document, migrating it from older versions, without validating it
+pub fn deserialize_level_json(bytes: &[u8]) -> Result<LevelData, LevelJSONAssetLoaderError> {
+    let mut level_json: serde_json::Value = serde_json::from_slice(bytes)?;
+
+    migrate_level(&mut level_json)?;
+
+    Ok(serde_json::from_value(level_json)?)
+}
+
 impl AssetLoader for LevelJSONAssetLoader {
     type Asset = LevelData;
     type Settings = ();
@@ -69,27 +64,18 @@ impl AssetLoader for LevelJSONAssetLoader {
         &'a self,
         reader: &'a mut Reader<'_>,
         _settings: &'a Self::Settings,
-        _load_context: &'a mut LoadContext<'_>,
+        load_context: &'a mut LoadContext<'_>,
     ) -> Result<LevelData, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
 
-        let level_json: LevelData = serde_json::from_slice(bytes.as_slice())?;
+        let level_data = deserialize_level_json(bytes.as_slice())?;
 
-        let mut tiles_set = HashSet::new();
+        let tile_registry = read_tile_registry(load_context).await?;
 
-        for tile in level_json.tiles.iter() {
-            if tiles_set.contains(&tile.off) {
-                return Err(InvalidLevelError::new(
-                    InvalidLevelErrorReason::DuplicateTileLocation(tile.off),
-                )
-                .into());
-            } else {
-                tiles_set.insert(tile.off);
-            }
-        }
+        validate_level(&level_data, &tile_registry)?;
 
-        Ok(level_json)
+        Ok(level_data)
     }
 
     fn extensions(&self) -> &[&str] {
@@ -104,9 +90,19 @@ fn load_level(
     mut set_level_event: EventReader<LoadLevelEvent>,
     asset_server: Res<AssetServer>,
     mut level_data_json_handle: ResMut<LevelDataHandleRes>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if let Some(level_id) = set_level_event.read().next() {
-        let level_handle = asset_server.load(format!("level/level{}.lvl.json", level_id.0));
+        let Some(entry) = level_manifests
+            .get(&level_manifest_handle.0)
+            .and_then(|manifest| manifest.get(level_id.0))
+        else {
+            println!("No level at index {} in the manifest", level_id.0);
+            return;
+        };
+
+        let level_handle = asset_server.load(entry.path.clone());
         level_data_json_handle.0 = Some(level_handle);
     }
 }
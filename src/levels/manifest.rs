@@ -0,0 +1,269 @@
+use std::io;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::levels::LoadLevelEvent;
+use crate::levels::registry::TileRegistry;
+
+pub const LEVEL_MANIFEST_PATH: &str = "levels.manifest.json";
+
+pub struct LevelManifestPlugin;
+
+impl Plugin for LevelManifestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelManifestHandleRes>()
+            .init_asset_loader::<LevelManifestAssetLoader>()
+            .init_asset::<LevelManifest>()
+            .add_systems(Startup, load_level_manifest)
+            .add_systems(Update, level_manifest_ready);
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LevelManifestEntry {
+    pub name: String,
+    /// Path of the level file, relative to the assets directory
+    pub path: String,
+    /// Time in seconds a good run of this level (day and night) should take
+    #[serde(default)]
+    pub par_time: Option<f32>,
+    /// The ending screen is loaded once every other level is finished, and is skipped when moving between levels
+    #[serde(default)]
+    pub ending: bool,
+}
+
+/// The levels of the game, in the order they are played
+#[derive(Deserialize, Serialize, Asset, TypePath, Debug)]
+pub struct LevelManifest {
+    pub levels: Vec<LevelManifestEntry>,
+}
+
+impl LevelManifest {
+    pub fn first_level(&self) -> Option<u32> {
+        self.levels
+            .iter()
+            .position(|entry| !entry.ending)
+            .map(|index| index as u32)
+    }
+
+    pub fn next_level(&self, current: u32) -> Option<u32> {
+        self.levels
+            .iter()
+            .enumerate()
+            .skip(current as usize + 1)
+            .find(|(_, entry)| !entry.ending)
+            .map(|(index, _)| index as u32)
+    }
+
+    /// None for levels that are not in the manifest, rather than the last level in it
+    pub fn previous_level(&self, current: u32) -> Option<u32> {
+        self.get(current)?;
+
+        self.levels
+            .iter()
+            .enumerate()
+            .take(current as usize)
+            .rev()
+            .find(|(_, entry)| !entry.ending)
+            .map(|(index, _)| index as u32)
+    }
+
+    pub fn ending_level(&self) -> Option<u32> {
+        self.levels
+            .iter()
+            .position(|entry| entry.ending)
+            .map(|index| index as u32)
+    }
+
+    pub fn get(&self, index: u32) -> Option<&LevelManifestEntry> {
+        self.levels.get(index as usize)
+    }
+
+    /// Sum of the par times of every playable level, only if all of them have one
+    pub fn total_par_time(&self) -> Option<f32> {
+        self.levels
+            .iter()
+            .filter(|entry| !entry.ending)
+            .map(|entry| entry.par_time)
+            .sum()
+    }
+}
+
+#[derive(Default)]
+struct LevelManifestAssetLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelManifestAssetLoaderError {
+    #[error("Could not parse json: {0}")]
+    SerdeParse(#[from] serde_json::Error),
+    #[error("Could not read file: {0}")]
+    IO(#[from] io::Error),
+    #[error("Manifest does not contain any playable levels")]
+    NoLevels,
+    #[error("Manifest contains more than one ending level")]
+    MultipleEndings,
+}
+
+pub fn parse_level_manifest(bytes: &[u8]) -> Result<LevelManifest, LevelManifestAssetLoaderError> {
+    let manifest: LevelManifest = serde_json::from_slice(bytes)?;
+
+    if manifest.first_level().is_none() {
+        return Err(LevelManifestAssetLoaderError::NoLevels);
+    }
+
+    if manifest.levels.iter().filter(|entry| entry.ending).count() > 1 {
+        return Err(LevelManifestAssetLoaderError::MultipleEndings);
+    }
+
+    Ok(manifest)
+}
+
+impl AssetLoader for LevelManifestAssetLoader {
+    type Asset = LevelManifest;
+    type Settings = ();
+    type Error = LevelManifestAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelManifest, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        parse_level_manifest(bytes.as_slice())
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.json"]
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LevelManifestHandleRes(pub Handle<LevelManifest>);
+
+fn load_level_manifest(
+    asset_server: Res<AssetServer>,
+    mut level_manifest_handle: ResMut<LevelManifestHandleRes>,
+) {
+    level_manifest_handle.0 = asset_server.load(LEVEL_MANIFEST_PATH);
+}
+
+/// Loads the first level once both the manifest and the tile registry are available, as building a level needs the
+/// registry. Later reloads of the manifest keep the current level
+fn level_manifest_ready(
+    mut load_level: EventWriter<LoadLevelEvent>,
+    mut started: Local<bool>,
+    tile_registry: Option<Res<TileRegistry>>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
+) {
+    if *started || tile_registry.is_none() {
+        return;
+    }
+
+    if let Some(first_level) = level_manifests
+        .get(&level_manifest_handle.0)
+        .and_then(|manifest| manifest.first_level())
+    {
+        *started = true;
+        load_level.send(LoadLevelEvent(first_level));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A manifest with a level for every par time, the ending is the one at the given index
+    fn manifest(par_times: &[Option<f32>], ending: Option<usize>) -> LevelManifest {
+        LevelManifest {
+            levels: par_times
+                .iter()
+                .enumerate()
+                .map(|(index, par_time)| LevelManifestEntry {
+                    name: format!("Level {}", index),
+                    path: format!("level/level{}.lvl.json", index),
+                    par_time: *par_time,
+                    ending: ending == Some(index),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn last_playable_level_has_no_next_level() {
+        let manifest = manifest(&[None, None, None], Some(2));
+
+        assert_eq!(manifest.next_level(0), Some(1));
+        assert_eq!(manifest.next_level(1), None);
+        assert_eq!(manifest.ending_level(), Some(2));
+    }
+
+    #[test]
+    fn ending_is_skipped_between_levels() {
+        let manifest = manifest(&[None, None, None], Some(1));
+
+        assert_eq!(manifest.next_level(0), Some(2));
+        assert_eq!(manifest.previous_level(2), Some(0));
+    }
+
+    #[test]
+    fn ending_first_is_not_the_first_level() {
+        let manifest = manifest(&[None, None], Some(0));
+
+        assert_eq!(manifest.first_level(), Some(1));
+        assert_eq!(manifest.previous_level(1), None);
+    }
+
+    #[test]
+    fn unknown_level_has_no_next_or_previous_level() {
+        let manifest = manifest(&[None, None], None);
+
+        assert_eq!(manifest.next_level(5), None);
+        assert_eq!(manifest.previous_level(5), None);
+        assert!(manifest.get(5).is_none());
+    }
+
+    #[test]
+    fn total_par_time_adds_up_playable_levels() {
+        let manifest = manifest(&[Some(10.), Some(20.5), None], Some(2));
+
+        assert_eq!(manifest.total_par_time(), Some(30.5));
+    }
+
+    #[test]
+    fn total_par_time_needs_every_playable_level() {
+        let manifest = manifest(&[Some(10.), None, Some(20.)], None);
+
+        assert_eq!(manifest.total_par_time(), None);
+    }
+
+    #[test]
+    fn manifest_with_only_an_ending_is_rejected() {
+        let bytes = br#"{"levels": [{"name": "The End", "path": "level/level999.lvl.json", "ending": true}]}"#;
+
+        assert!(matches!(
+            parse_level_manifest(bytes),
+            Err(LevelManifestAssetLoaderError::NoLevels)
+        ));
+    }
+
+    #[test]
+    fn manifest_with_two_endings_is_rejected() {
+        let bytes = br#"{"levels": [
+            {"name": "Level 1", "path": "level/level0.lvl.json"},
+            {"name": "The End", "path": "level/level998.lvl.json", "ending": true},
+            {"name": "The End", "path": "level/level999.lvl.json", "ending": true}
+        ]}"#;
+
+        assert!(matches!(
+            parse_level_manifest(bytes),
+            Err(LevelManifestAssetLoaderError::MultipleEndings)
+        ));
+    }
+}
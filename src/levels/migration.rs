@@ -0,0 +1,207 @@
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Version written by the editor, older level files are migrated up to it when loaded
+pub const CURRENT_LEVEL_VERSION: u32 = 2;
+
+const VERSION_KEY: &str = "version";
+
+/// Levels from before they declared their own size were played through a fixed camera showing 20 columns, and ended
+/// at the default exit right after the last of them
+const LEGACY_LEVEL_WIDTH: u32 = 20;
+/// The fixed camera showed 15 rows, the levels from then use the row above it as a ceiling the player can not see
+const LEGACY_LEVEL_HEIGHT: u32 = 16;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// Migration at index `n` upgrades a level from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; CURRENT_LEVEL_VERSION as usize] =
+    [migrate_unversioned, migrate_add_level_size];
+
+#[derive(Debug, Error)]
+pub enum LevelMigrationError {
+    #[error("Level must be a json object")]
+    NotAnObject,
+    #[error("Level version must be an unsigned integer, found: {0}")]
+    InvalidVersion(Value),
+    #[error(
+        "Level version {0} is newer than the newest supported version {}",
+        CURRENT_LEVEL_VERSION
+    )]
+    UnsupportedVersion(u64),
+}
+
+/// Upgrades a level json document in place to [`CURRENT_LEVEL_VERSION`], levels without a version are version 0
+pub fn migrate_level(level_json: &mut Value) -> Result<(), LevelMigrationError> {
+    let level = level_json
+        .as_object_mut()
+        .ok_or(LevelMigrationError::NotAnObject)?;
+
+    let version = match level.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| LevelMigrationError::InvalidVersion(version.clone()))?,
+    };
+
+    if version > CURRENT_LEVEL_VERSION as u64 {
+        return Err(LevelMigrationError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(level);
+    }
+
+    level.insert(VERSION_KEY.into(), CURRENT_LEVEL_VERSION.into());
+
+    Ok(())
+}
+
+/// Levels from before versioning, every field added since then has a serde default so nothing needs to change
+fn migrate_unversioned(_level: &mut Map<String, Value>) {}
+
+/// Levels declare their width and height since version 2. Older levels get the size the fixed camera showed, grown to
+/// fit any tile or spawn location placed outside of it
+fn migrate_add_level_size(level: &mut Map<String, Value>) {
+    let locations = level
+        .get("tiles")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|tile| tile.get("off"))
+        .chain(level.get("spawn_location"))
+        .filter_map(|loc| loc.as_str()?.split_once(','))
+        .filter_map(|(x, y)| Some((x.parse::<u32>().ok()?, y.parse::<u32>().ok()?)))
+        .collect::<Vec<_>>();
+
+    let width = locations
+        .iter()
+        .map(|(x, _)| x.saturating_add(1))
+        .fold(LEGACY_LEVEL_WIDTH, u32::max);
+    let height = locations
+        .iter()
+        .map(|(_, y)| y.saturating_add(1))
+        .fold(LEGACY_LEVEL_HEIGHT, u32::max);
+
+    level.entry("width").or_insert_with(|| width.into());
+    level.entry("height").or_insert_with(|| height.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::UVec2;
+    use serde_json::json;
+
+    use crate::levels::test_support::shipped_level;
+
+    use super::*;
+
+    #[test]
+    fn unversioned_level_is_migrated_to_current_version() {
+        let mut level_json = json!({"spawn_location": "0,0", "tiles": []});
+
+        migrate_level(&mut level_json).unwrap();
+
+        assert_eq!(level_json[VERSION_KEY], json!(CURRENT_LEVEL_VERSION));
+        assert_eq!(level_json["width"], json!(LEGACY_LEVEL_WIDTH));
+        assert_eq!(level_json["height"], json!(LEGACY_LEVEL_HEIGHT));
+    }
+
+    #[test]
+    fn shipped_levels_are_migrated_to_legacy_size() {
+        for path in ["level/level0.lvl.json", "level/level1.lvl.json"] {
+            let level_data = shipped_level(path);
+
+            assert_eq!(level_data.version, CURRENT_LEVEL_VERSION);
+            assert_eq!(
+                level_data.size(),
+                UVec2::new(LEGACY_LEVEL_WIDTH, LEGACY_LEVEL_HEIGHT)
+            );
+        }
+    }
+
+    #[test]
+    fn unversioned_migration_changes_nothing() {
+        let mut level = json!({"spawn_location": "0,0", "tiles": []});
+        let before = level.clone();
+
+        migrate_unversioned(level.as_object_mut().unwrap());
+
+        assert_eq!(level, before);
+    }
+
+    #[test]
+    fn add_level_size_fills_in_legacy_size() {
+        let mut level = json!({"version": 1});
+
+        migrate_add_level_size(level.as_object_mut().unwrap());
+
+        assert_eq!(level["width"], json!(LEGACY_LEVEL_WIDTH));
+        assert_eq!(level["height"], json!(LEGACY_LEVEL_HEIGHT));
+    }
+
+    #[test]
+    fn add_level_size_grows_to_fit_tiles_and_spawn() {
+        let mut level = json!({
+            "version": 1,
+            "spawn_location": "3,20",
+            "tiles": [{"tile_type": "Dirt", "off": "25,2", "over": null}],
+        });
+
+        migrate_add_level_size(level.as_object_mut().unwrap());
+
+        assert_eq!(level["width"], json!(26));
+        assert_eq!(level["height"], json!(21));
+    }
+
+    #[test]
+    fn add_level_size_keeps_existing_size() {
+        let mut level = json!({"version": 1, "width": 20, "height": 16});
+
+        migrate_add_level_size(level.as_object_mut().unwrap());
+
+        assert_eq!(level["width"], json!(20));
+        assert_eq!(level["height"], json!(16));
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let mut level_json = json!({"version": CURRENT_LEVEL_VERSION, "width": 20, "height": 16});
+        let before = level_json.clone();
+
+        migrate_level(&mut level_json).unwrap();
+
+        assert_eq!(level_json, before);
+    }
+
+    #[test]
+    fn newer_version_is_unsupported() {
+        let mut level_json = json!({"version": CURRENT_LEVEL_VERSION + 1});
+
+        assert!(matches!(
+            migrate_level(&mut level_json),
+            Err(LevelMigrationError::UnsupportedVersion(version))
+                if version == CURRENT_LEVEL_VERSION as u64 + 1
+        ));
+    }
+
+    #[test]
+    fn invalid_version_is_rejected() {
+        let mut level_json = json!({"version": "two"});
+
+        assert!(matches!(
+            migrate_level(&mut level_json),
+            Err(LevelMigrationError::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn non_object_is_rejected() {
+        let mut level_json = json!([]);
+
+        assert!(matches!(
+            migrate_level(&mut level_json),
+            Err(LevelMigrationError::NotAnObject)
+        ));
+    }
+}
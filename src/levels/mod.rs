@@ -1,49 +1,79 @@
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy_ecs_tilemap::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::day_night::{DayCycleSet, DayNightState, NightCycleSet};
 use crate::debug::{DebugState, DebugUpdateSet, DebugVisibility};
-use crate::levels::data::{LevelData, LocationData, TileTypeData};
-use crate::levels::hazard::HazardPlugin;
+use crate::levels::collider::{merge_tiles, TileRect};
+use crate::levels::data::{LevelData, LocationData, TileData, TileTypeData};
+use crate::levels::hazard::{Hazard, HazardPlugin};
 use crate::levels::level_loader::{LevelDataLoadedEvent, LevelLoaderPlugin};
+use crate::levels::manifest::{LevelManifest, LevelManifestHandleRes, LevelManifestPlugin};
+use crate::levels::registry::{TileRegistry, TileRegistryPlugin};
+use crate::levels::snow::SnowPlugin;
 use crate::math::tile_pos_to_world_pos;
 use crate::player::respawn::RespawnPlayerEvent;
 use crate::timer::StartGameEvent;
 use crate::win::WinGameEvent;
 use crate::z_indices::{TILE_MAP_OVERLAY_Z_INDEX, TILE_MAP_Z_INDEX};
 
+pub mod ascii;
+mod collider;
 pub mod data;
-mod hazard;
+pub mod format;
+pub mod hazard;
+pub mod heat;
 pub mod level_loader;
+pub mod manifest;
+pub mod migration;
+pub mod registry;
+pub mod rle;
+pub mod snow;
+pub mod solver;
+#[cfg(test)]
+mod test_support;
+pub mod tiled;
+pub mod validation;
 
-pub const TILE_MAP_SIZE: u32 = 32;
 pub const TILE_SIZE: f32 = 16.;
 pub const HALF_TILE_SIZE: f32 = 8.;
 
-pub const MAX_LEVEL_INDEX: u32 = 5;
-
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((LevelLoaderPlugin, HazardPlugin))
-            .add_event::<LoadLevelEvent>()
-            .add_event::<LoadPreviousLevelEvent>()
-            .add_event::<LoadNextLevelEvent>()
-            .add_event::<TileLevelLoadedEvent>()
-            .init_resource::<CurrentLevel>()
-            .add_systems(Startup, setup)
-            .add_systems(
-                Update,
+        app.add_plugins((
+            LevelLoaderPlugin,
+            LevelManifestPlugin,
+            TileRegistryPlugin,
+            HazardPlugin,
+            SnowPlugin,
+        ))
+        .add_event::<LoadLevelEvent>()
+        .add_event::<LoadPreviousLevelEvent>()
+        .add_event::<LoadNextLevelEvent>()
+        .add_event::<TileLevelLoadedEvent>()
+        .add_event::<RebuildLevelEvent>()
+        .init_resource::<CurrentLevel>()
+        .init_resource::<BuiltLevel>()
+        .init_resource::<PendingLevel>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
                 (
-                    level_data_ready,
-                    receive_load_level,
-                    receive_load_next_level,
-                    receive_load_previous_level,
-                    (debug_toggle_tilemap_visibility, debug_next_level).in_set(DebugUpdateSet),
-                ),
-            );
+                    rebuild_night_level.in_set(NightCycleSet),
+                    rebuild_day_level.in_set(DayCycleSet),
+                )
+                    .before(level_data_ready),
+                level_data_ready,
+                receive_load_level,
+                receive_load_next_level,
+                receive_load_previous_level,
+                (debug_toggle_tilemap_visibility, debug_next_level).in_set(DebugUpdateSet),
+            ),
+        );
     }
 }
 
@@ -53,6 +83,7 @@ pub struct MainMap;
 #[derive(Component)]
 pub struct OverlayMap;
 
+/// Loads the level at the given index of the [`LevelManifest`]
 #[derive(Event)]
 pub struct LoadLevelEvent(pub u32);
 
@@ -68,156 +99,453 @@ pub struct CurrentLevel(pub u32);
 #[derive(Event)]
 pub struct TileLevelLoadedEvent {
     pub level_data_map: HashMap<LocationData, TileTypeData>,
+    /// Size of the level in tiles
+    pub size: UVec2,
 }
 
-fn setup(mut load_level: EventWriter<LoadLevelEvent>, mut start_game: EventWriter<StartGameEvent>) {
-    load_level.send(LoadLevelEvent(0));
+/// Rebuilds the tile maps of the level being played with the tiles of the current run
+#[derive(Event, Default)]
+struct RebuildLevelEvent;
+
+fn setup(mut start_game: EventWriter<StartGameEvent>) {
     start_game.send_default();
 }
 
+/// The level the tile maps were built from and the run they were built for, so a hot reload of the same level or a
+/// switch between day and night only has to change what is different
+#[derive(Resource, Default)]
+struct BuiltLevel(Option<(AssetId<LevelData>, LevelData, DayNightState)>);
+
+/// A level that finished loading before the tile registry did, built as soon as the registry is there
+#[derive(Resource, Default)]
+struct PendingLevel(Option<AssetId<LevelData>>);
+
+/// Collider covering merged solid tiles, rebuilt whenever any tile changes
+#[derive(Component)]
+struct SolidTileCollider;
+
+/// Fraction of the sunlight a ray towards the sun keeps after passing through the collider. Colliders without it
+/// block the sun completely
+#[derive(Component)]
+pub struct SunTransmittance(pub f32);
+
+/// Collider of tiles that refreeze the player standing on them
+#[derive(Component)]
+pub struct Refreezing;
+
+type MapStorage<'a> = (Entity, &'a mut TileStorage);
+
 #[allow(clippy::too_many_arguments)]
 fn level_data_ready(
     mut commands: Commands,
     tile_map_query: Query<Entity, With<TilemapType>>,
+    mut main_map_query: Query<MapStorage, (With<MainMap>, Without<OverlayMap>)>,
+    mut overlay_map_query: Query<MapStorage, (With<OverlayMap>, Without<MainMap>)>,
+    solid_collider_query: Query<Entity, With<SolidTileCollider>>,
     mut level_data_loaded_event: EventReader<LevelDataLoadedEvent>,
+    mut rebuild_level_event: EventReader<RebuildLevelEvent>,
     mut respawn_player: EventWriter<RespawnPlayerEvent>,
     mut tile_level_loaded: EventWriter<TileLevelLoadedEvent>,
+    mut built_level: ResMut<BuiltLevel>,
+    mut pending_level: ResMut<PendingLevel>,
     debug_state: Res<State<DebugState>>,
+    day_night_state: Res<State<DayNightState>>,
+    tile_registry: Option<Res<TileRegistry>>,
     level_data_assets: Res<Assets<LevelData>>,
     asset_server: Res<AssetServer>,
 ) {
-    if let Some(level_data) = level_data_loaded_event.read().next() {
-        let level_data = level_data_assets.get(level_data.0).unwrap();
+    // the events are read every frame, as they are dropped after a couple of frames whether they were read or not
+    if let Some(level_data_loaded) = level_data_loaded_event.read().next() {
+        pending_level.0 = Some(level_data_loaded.0);
+    }
 
-        let mut level_data_map = HashMap::new();
+    let rebuild_level_id = rebuild_level_event
+        .read()
+        .next()
+        .and(built_level.0.as_ref())
+        .map(|(id, _, _)| *id);
+
+    let Some(tile_registry) = tile_registry else {
+        return;
+    };
+
+    let Some(level_id) = pending_level.0.take().or(rebuild_level_id) else {
+        return;
+    };
+
+    let level_data = level_data_assets.get(level_id).unwrap();
+    let run = *day_night_state.get();
+
+    // the night run is played on the tiles as they are at night
+    let level_data = &match run {
+        DayNightState::Day => level_data.clone(),
+        DayNightState::Night => LevelData {
+            tiles: tile_registry.night_tiles(&level_data.tiles),
+            ..level_data.clone()
+        },
+    };
+
+    let debug_visibility = match debug_state.get() {
+        DebugState::On => Visibility::Visible,
+        DebugState::Off => Visibility::Hidden,
+    };
+
+    // a hot reload of the level being played, which can be applied to the existing tile maps unless its size changed
+    let built_tiles = built_level
+        .0
+        .as_ref()
+        .filter(|(id, built, _)| *id == level_id && built.size() == level_data.size())
+        .map(|(_, built, _)| &built.tiles);
+
+    match (
+        built_tiles,
+        main_map_query.get_single_mut(),
+        overlay_map_query.get_single_mut(),
+    ) {
+        (
+            Some(built_tiles),
+            Ok((tile_map_entity, mut tile_storage)),
+            Ok((overlay_tile_map_entity, mut overlay_tile_storage)),
+        ) => {
+            let old_tiles = built_tiles
+                .iter()
+                .map(|tile_data| (tile_data.off, tile_data))
+                .collect::<HashMap<_, _>>();
+            let new_tiles = level_data
+                .tiles
+                .iter()
+                .map(|tile_data| (tile_data.off, tile_data))
+                .collect::<HashMap<_, _>>();
+
+            let changed = old_tiles
+                .keys()
+                .chain(new_tiles.keys())
+                .filter(|loc| old_tiles.get(loc) != new_tiles.get(loc))
+                .copied()
+                .collect::<HashSet<_>>();
+
+            for loc in &changed {
+                let tile_pos = (*loc).into();
+
+                for storage in [&mut *tile_storage, &mut *overlay_tile_storage] {
+                    if let Some(entity) = storage.get(&tile_pos) {
+                        commands.entity(entity).despawn_recursive();
+                        storage.remove(&tile_pos);
+                    }
+                }
+
+                if let Some(tile_data) = new_tiles.get(loc) {
+                    spawn_tile(
+                        &mut commands,
+                        tile_data,
+                        (tile_map_entity, &mut *tile_storage),
+                        (overlay_tile_map_entity, &mut *overlay_tile_storage),
+                        debug_visibility,
+                        &tile_registry,
+                    );
+                }
+            }
+
+            if !changed.is_empty() {
+                for collider_entity in solid_collider_query.iter() {
+                    commands.entity(collider_entity).despawn();
+                }
+
+                spawn_solid_colliders(&mut commands, level_data, &tile_registry, tile_map_entity);
+            }
 
-        for tile_map_entity in tile_map_query.iter() {
-            commands.entity(tile_map_entity).despawn_recursive();
+            println!("Rebuilt level, {} tile(s) changed", changed.len());
         }
+        _ => {
+            for tile_map_entity in tile_map_query.iter() {
+                commands.entity(tile_map_entity).despawn_recursive();
+            }
 
-        let tile_map_size = TilemapSize::new(TILE_MAP_SIZE, TILE_MAP_SIZE);
+            let tile_map_size = TilemapSize::new(level_data.width, level_data.height);
 
-        let tile_set_handle: Handle<Image> = asset_server.load("image/tile/texture_map.png");
-        let mut tile_storage = TileStorage::empty(tile_map_size);
-        let tile_map_entity = commands.spawn_empty().id();
+            let tile_set_handle: Handle<Image> = asset_server.load("image/tile/texture_map.png");
+            let mut tile_storage = TileStorage::empty(tile_map_size);
+            let tile_map_entity = commands.spawn_empty().id();
 
-        let mut overlay_tile_storage = TileStorage::empty(tile_map_size);
-        let overlay_tile_map_entity = commands.spawn_empty().id();
+            let mut overlay_tile_storage = TileStorage::empty(tile_map_size);
+            let overlay_tile_map_entity = commands.spawn_empty().id();
 
-        for tile_data in &level_data.tiles {
-            let tile_pos = TilePos::new(tile_data.off.x, tile_data.off.y);
+            for tile_data in &level_data.tiles {
+                spawn_tile(
+                    &mut commands,
+                    tile_data,
+                    (tile_map_entity, &mut tile_storage),
+                    (overlay_tile_map_entity, &mut overlay_tile_storage),
+                    debug_visibility,
+                    &tile_registry,
+                );
+            }
 
-            level_data_map.insert(tile_data.off, tile_data.tile_type);
+            spawn_solid_colliders(&mut commands, level_data, &tile_registry, tile_map_entity);
+
+            let tile_size = TilemapTileSize::new(TILE_SIZE, TILE_SIZE);
+            let grid_size = tile_size.into();
+
+            commands.entity(tile_map_entity).insert((
+                TilemapBundle {
+                    grid_size,
+                    size: tile_map_size,
+                    storage: tile_storage,
+                    tile_size,
+                    texture: TilemapTexture::Single(tile_set_handle.clone()),
+                    transform: Transform::from_xyz(0., 0., TILE_MAP_Z_INDEX),
+                    ..default()
+                },
+                MainMap,
+            ));
+
+            commands.entity(overlay_tile_map_entity).insert((
+                TilemapBundle {
+                    grid_size,
+                    size: tile_map_size,
+                    storage: overlay_tile_storage,
+                    tile_size,
+                    texture: TilemapTexture::Single(tile_set_handle),
+                    transform: Transform::from_xyz(0., 0., TILE_MAP_OVERLAY_Z_INDEX),
+                    ..default()
+                },
+                OverlayMap,
+            ));
+
+            respawn_player.send_default();
+        }
+    }
 
-            let tile_entity = commands
-                .spawn((
-                    TileBundle {
-                        position: tile_pos,
-                        tilemap_id: TilemapId(tile_map_entity),
-                        texture_index: TileTextureIndex(tile_data.tile_type.texture_index()),
+    built_level.0 = Some((level_id, level_data.clone(), run));
+
+    tile_level_loaded.send(TileLevelLoadedEvent {
+        level_data_map: level_data
+            .tiles
+            .iter()
+            .map(|tile_data| (tile_data.off, tile_data.tile_type.clone()))
+            .collect(),
+        size: level_data.size(),
+    });
+}
+
+fn rebuild_night_level(
+    built_level: Res<BuiltLevel>,
+    mut rebuild_level: EventWriter<RebuildLevelEvent>,
+) {
+    if built_level
+        .0
+        .as_ref()
+        .is_some_and(|(_, _, run)| *run == DayNightState::Day)
+    {
+        rebuild_level.send_default();
+    }
+}
+
+fn rebuild_day_level(
+    built_level: Res<BuiltLevel>,
+    mut rebuild_level: EventWriter<RebuildLevelEvent>,
+) {
+    if built_level
+        .0
+        .as_ref()
+        .is_some_and(|(_, _, run)| *run == DayNightState::Night)
+    {
+        rebuild_level.send_default();
+    }
+}
+
+/// Spawns a tile and its overlay into the main and overlay maps, with a sensor for hazards. Solid tiles get their
+/// collider from [`spawn_solid_colliders`]
+fn spawn_tile(
+    commands: &mut Commands,
+    tile_data: &TileData,
+    (tile_map_entity, tile_storage): (Entity, &mut TileStorage),
+    (overlay_tile_map_entity, overlay_tile_storage): (Entity, &mut TileStorage),
+    debug_visibility: Visibility,
+    tile_registry: &TileRegistry,
+) {
+    // levels are validated against the registry when they load, so this only happens while the registry is reloading
+    let Some(tile_definition) = tile_registry.tile(&tile_data.tile_type) else {
+        println!(
+            "Skipping tile {} at {} which is not in the tile registry",
+            tile_data.tile_type, tile_data.off
+        );
+        return;
+    };
+
+    let tile_pos = TilePos::new(tile_data.off.x, tile_data.off.y);
+
+    let tile_entity = commands
+        .spawn((
+            TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(tile_map_entity),
+                texture_index: TileTextureIndex(tile_definition.atlas_index),
+                ..default()
+            },
+            TransformBundle::from_transform(Transform::from_translation(tile_pos_to_world_pos(
+                tile_pos.into(),
+                0.,
+            ))),
+            InheritedVisibility::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(format!("{}, {}", tile_pos.x, tile_pos.y), default()),
+                    transform: Transform {
+                        translation: Vec3::new(0., 0., 1.),
+                        scale: Vec3::splat(0.2),
                         ..default()
                     },
-                    TransformBundle::from_transform(Transform::from_translation(
-                        tile_pos_to_world_pos(tile_pos.into(), 0.),
-                    )),
-                    InheritedVisibility::default(),
-                ))
-                .id();
+                    visibility: debug_visibility,
+                    ..default()
+                },
+                DebugVisibility,
+            ));
+        })
+        .id();
+
+    if let Some(overlay_definition) = tile_data
+        .over
+        .as_ref()
+        .and_then(|over| tile_registry.overlay(over))
+    {
+        let overlay_tile_entity = commands
+            .spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(overlay_tile_map_entity),
+                texture_index: TileTextureIndex(overlay_definition.atlas_index),
+                ..default()
+            })
+            .id();
 
-            if let Some(over) = &tile_data.over {
-                let overlay_tile_entity = commands
-                    .spawn(TileBundle {
-                        position: tile_pos,
-                        tilemap_id: TilemapId(overlay_tile_map_entity),
-                        texture_index: TileTextureIndex(over.texture_index()),
-                        ..default()
-                    })
-                    .id();
+        overlay_tile_storage.set(&tile_pos, overlay_tile_entity);
+    }
 
-                overlay_tile_storage.set(&tile_pos, overlay_tile_entity);
-            }
+    if let Some(heat_source) = tile_definition.heat {
+        commands.entity(tile_entity).insert(heat_source);
+    }
+
+    // tiles the player passes through still need a collider for the sun to hit, if they shade it at all
+    let shades = !tile_definition.solid && tile_definition.sun_transmittance < 1.;
+
+    if tile_definition.hazard.is_some() || shades {
+        let collider_entity = commands
+            .spawn((
+                TransformBundle::default(),
+                Collider::cuboid(TILE_SIZE / 2., TILE_SIZE / 2.),
+                Sensor,
+            ))
+            .id();
+
+        if let Some(kind) = tile_definition.hazard {
+            commands
+                .entity(collider_entity)
+                .insert((ActiveEvents::COLLISION_EVENTS, Hazard { kind, tile_pos }));
+        }
+
+        if tile_definition.sun_transmittance > 0. {
+            commands
+                .entity(collider_entity)
+                .insert(SunTransmittance(tile_definition.sun_transmittance));
+        }
+
+        commands.entity(tile_entity).add_child(collider_entity);
+    }
+
+    commands.entity(tile_map_entity).add_child(tile_entity);
+    tile_storage.set(&tile_pos, tile_entity);
+}
+
+/// One collider per tile makes for a lot of bodies, and shape casts catch on the edges between them, so solid tiles
+/// are merged into rectangles. Only tiles with the same friction, sun transmittance and refreezing are merged with each
+/// other, and tiles that let some of the sun through are not merged at all
+fn spawn_solid_colliders(
+    commands: &mut Commands,
+    level_data: &LevelData,
+    tile_registry: &TileRegistry,
+    tile_map_entity: Entity,
+) {
+    let mut solid_tile_groups: HashMap<(u32, u32, bool), HashSet<LocationData>> = HashMap::new();
+
+    for tile_data in &level_data.tiles {
+        let Some(tile_definition) = tile_registry
+            .tile(&tile_data.tile_type)
+            .filter(|tile_definition| tile_definition.solid)
+        else {
+            continue;
+        };
+
+        solid_tile_groups
+            .entry((
+                tile_definition.friction.to_bits(),
+                tile_definition.sun_transmittance.to_bits(),
+                tile_definition.refreezes,
+            ))
+            .or_default()
+            .insert(tile_data.off);
+    }
+
+    for ((friction, sun_transmittance, refreezes), solid_tiles) in solid_tile_groups {
+        let sun_transmittance = f32::from_bits(sun_transmittance);
+
+        // a ray towards the sun loses a share of it for every one of these tiles it crosses, the same as in the shadows
+        // and the solver, which it could not tell apart inside a merged rectangle
+        let rects = if sun_transmittance > 0. {
+            solid_tiles
+                .iter()
+                .map(|loc| TileRect {
+                    min: (*loc).into(),
+                    max: (*loc).into(),
+                })
+                .collect()
+        } else {
+            merge_tiles(&solid_tiles)
+        };
+
+        for rect in rects {
+            let half_size = rect.half_size();
 
             let collider_entity = commands
                 .spawn((
-                    TransformBundle::default(),
-                    Collider::cuboid(TILE_SIZE / 2., TILE_SIZE / 2.),
-                    InheritedVisibility::default(),
+                    TransformBundle::from_transform(Transform::from_translation(
+                        rect.center().extend(0.),
+                    )),
+                    Collider::cuboid(half_size.x, half_size.y),
+                    Friction::coefficient(f32::from_bits(friction)),
+                    SolidTileCollider,
                 ))
-                .with_children(|parent| {
-                    parent.spawn((
-                        Text2dBundle {
-                            text: Text::from_section(
-                                format!("{}, {}", tile_pos.x, tile_pos.y),
-                                default(),
-                            ),
-                            transform: Transform {
-                                translation: Vec3::new(0., 0., 1.),
-                                scale: Vec3::splat(0.2),
-                                ..default()
-                            },
-                            visibility: match debug_state.get() {
-                                DebugState::On => Visibility::Visible,
-                                DebugState::Off => Visibility::Hidden,
-                            },
-                            ..default()
-                        },
-                        DebugVisibility,
-                    ));
-                })
                 .id();
 
-            if tile_data.tile_type.is_hazard() {
+            if sun_transmittance > 0. {
                 commands
                     .entity(collider_entity)
-                    .insert((Sensor, ActiveEvents::COLLISION_EVENTS));
+                    .insert(SunTransmittance(sun_transmittance));
             }
 
-            commands.entity(tile_entity).add_child(collider_entity);
+            if refreezes {
+                commands.entity(collider_entity).insert(Refreezing);
+            }
 
-            commands.entity(tile_map_entity).add_child(tile_entity);
-            tile_storage.set(&tile_pos, tile_entity);
+            commands.entity(tile_map_entity).add_child(collider_entity);
         }
-
-        let tile_size = TilemapTileSize::new(TILE_SIZE, TILE_SIZE);
-        let grid_size = tile_size.into();
-
-        commands.entity(tile_map_entity).insert((
-            TilemapBundle {
-                grid_size,
-                size: tile_map_size,
-                storage: tile_storage,
-                tile_size,
-                texture: TilemapTexture::Single(tile_set_handle.clone()),
-                transform: Transform::from_xyz(0., 0., TILE_MAP_Z_INDEX),
-                ..default()
-            },
-            MainMap,
-        ));
-
-        commands.entity(overlay_tile_map_entity).insert((
-            TilemapBundle {
-                grid_size,
-                size: tile_map_size,
-                storage: overlay_tile_storage,
-                tile_size,
-                texture: TilemapTexture::Single(tile_set_handle),
-                transform: Transform::from_xyz(0., 0., TILE_MAP_OVERLAY_Z_INDEX),
-                ..default()
-            },
-            OverlayMap,
-        ));
-
-        respawn_player.send_default();
-        tile_level_loaded.send(TileLevelLoadedEvent { level_data_map });
     }
 }
 
 fn receive_load_level(
     mut load_level_event: EventReader<LoadLevelEvent>,
     mut current_level: ResMut<CurrentLevel>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if let Some(load_level) = load_level_event.read().next() {
-        println!("Loading level: {}", load_level.0);
+        let name = level_manifests
+            .get(&level_manifest_handle.0)
+            .and_then(|manifest| manifest.get(load_level.0))
+            .map_or("unknown", |entry| entry.name.as_str());
+
+        println!("Loading level: {} ({})", load_level.0, name);
         current_level.0 = load_level.0;
     }
 }
@@ -227,10 +555,16 @@ fn receive_load_next_level(
     mut load_next_level_event: EventReader<LoadNextLevelEvent>,
     mut load_level_event: EventWriter<LoadLevelEvent>,
     mut win_game_event: EventWriter<WinGameEvent>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if load_next_level_event.read().next().is_some() {
-        if current_level.0 < MAX_LEVEL_INDEX {
-            load_level_event.send(LoadLevelEvent(current_level.0 + 1));
+        let Some(manifest) = level_manifests.get(&level_manifest_handle.0) else {
+            return;
+        };
+
+        if let Some(next_level) = manifest.next_level(current_level.0) {
+            load_level_event.send(LoadLevelEvent(next_level));
         } else {
             win_game_event.send_default();
             println!("WIN!");
@@ -242,10 +576,16 @@ fn receive_load_previous_level(
     current_level: Res<CurrentLevel>,
     mut load_previous_level_event: EventReader<LoadPreviousLevelEvent>,
     mut load_level_event: EventWriter<LoadLevelEvent>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if load_previous_level_event.read().next().is_some() {
-        if current_level.0 > 0 {
-            load_level_event.send(LoadLevelEvent(current_level.0 - 1));
+        let Some(manifest) = level_manifests.get(&level_manifest_handle.0) else {
+            return;
+        };
+
+        if let Some(previous_level) = manifest.previous_level(current_level.0) {
+            load_level_event.send(LoadLevelEvent(previous_level));
         } else {
             println!("No further back");
         }
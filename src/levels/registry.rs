@@ -0,0 +1,621 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext, ReadAssetBytesError};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::levels::ascii::RESERVED_GLYPHS;
+use crate::levels::data::{OverlayData, TileData, TileTypeData};
+use crate::levels::hazard::HazardKind;
+use crate::levels::heat::HeatSource;
+
+pub const TILE_REGISTRY_PATH: &str = "tiles.registry.json";
+/// Directory the asset server loads from, for the tools that read assets without creating the bevy app
+pub const ASSET_DIRECTORY: &str = "assets";
+
+pub struct TileRegistryPlugin;
+
+impl Plugin for TileRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileRegistryHandleRes>()
+            .init_asset_loader::<TileRegistryAssetLoader>()
+            .init_asset::<TileRegistry>()
+            .add_systems(Startup, load_tile_registry)
+            .add_systems(Update, tile_registry_ready);
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TileDefinition {
+    /// Name levels refer to the tile by
+    pub name: TileTypeData,
+    pub atlas_index: u32,
+    /// Whether the player collides with the tile
+    pub solid: bool,
+    /// Kills the player on contact
+    #[serde(default)]
+    pub hazard: Option<HazardKind>,
+    pub friction: f32,
+    /// Fraction of the sunlight that passes through the tile, none for a full shadow
+    #[serde(default)]
+    pub sun_transmittance: f32,
+    /// Slowly undoes the player's melting while they stand on the tile
+    #[serde(default)]
+    pub refreezes: bool,
+    #[serde(default)]
+    pub heat: Option<HeatSource>,
+    #[serde(default)]
+    pub at_night: NightChange,
+    /// Radius in tiles of the light the tile gives off at night
+    #[serde(default)]
+    pub light: Option<f32>,
+    /// Lower case letter the tile is written as in run length encoded levels, which can not hold it without one
+    #[serde(default)]
+    pub rle_code: Option<char>,
+    /// Character the tile is drawn as in ascii levels, which can not hold it without one
+    #[serde(default)]
+    pub ascii_glyph: Option<char>,
+}
+
+/// What happens to a tile during the night run
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum NightChange {
+    #[default]
+    Unchanged,
+    /// Replaced by another tile of the registry, keeping its overlay
+    Becomes(TileTypeData),
+    /// Gone along with its overlay, for platforms that only exist in daylight
+    Vanishes,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OverlayDefinition {
+    pub name: OverlayData,
+    pub atlas_index: u32,
+    /// Tiles with the overlay on top are written with the upper case of their code in run length encoded levels, only
+    /// one overlay can be
+    #[serde(default)]
+    pub rle_upper_case: bool,
+    #[serde(default)]
+    pub ascii_glyph: Option<OverlayGlyph>,
+}
+
+/// Character an overlay is drawn as in ascii levels, which always puts it on top of the same tile
+#[derive(Deserialize, Debug, Clone)]
+pub struct OverlayGlyph {
+    pub glyph: char,
+    pub on: TileTypeData,
+}
+
+/// Every tile and overlay levels can use. The loaded registry is copied into a resource, so systems can use it
+/// without looking it up in its assets
+#[derive(Deserialize, Asset, Resource, TypePath, Debug, Clone)]
+pub struct TileRegistry {
+    pub tiles: Vec<TileDefinition>,
+    #[serde(default)]
+    pub overlays: Vec<OverlayDefinition>,
+}
+
+impl TileRegistry {
+    pub fn tile(&self, tile_type: &TileTypeData) -> Option<&TileDefinition> {
+        self.tiles.iter().find(|tile| tile.name == *tile_type)
+    }
+
+    pub fn overlay(&self, over: &OverlayData) -> Option<&OverlayDefinition> {
+        self.overlays.iter().find(|overlay| overlay.name == *over)
+    }
+
+    pub fn tile_by_atlas_index(&self, atlas_index: u32) -> Option<&TileDefinition> {
+        self.tiles
+            .iter()
+            .find(|tile| tile.atlas_index == atlas_index)
+    }
+
+    pub fn overlay_by_atlas_index(&self, atlas_index: u32) -> Option<&OverlayDefinition> {
+        self.overlays
+            .iter()
+            .find(|overlay| overlay.atlas_index == atlas_index)
+    }
+
+    pub fn tile_by_rle_code(&self, code: char) -> Option<&TileDefinition> {
+        self.tiles.iter().find(|tile| tile.rle_code == Some(code))
+    }
+
+    pub fn upper_case_overlay(&self) -> Option<&OverlayDefinition> {
+        self.overlays.iter().find(|overlay| overlay.rle_upper_case)
+    }
+
+    pub fn tile_by_ascii_glyph(&self, glyph: char) -> Option<&TileDefinition> {
+        self.tiles
+            .iter()
+            .find(|tile| tile.ascii_glyph == Some(glyph))
+    }
+
+    /// Unknown tiles are neither solid nor hazards, levels using them do not pass validation
+    pub fn is_solid(&self, tile_type: &TileTypeData) -> bool {
+        self.tile(tile_type).is_some_and(|tile| tile.solid)
+    }
+
+    pub fn hazard_kind(&self, tile_type: &TileTypeData) -> Option<HazardKind> {
+        self.tile(tile_type).and_then(|tile| tile.hazard)
+    }
+
+    pub fn is_hazard(&self, tile_type: &TileTypeData) -> bool {
+        self.hazard_kind(tile_type).is_some()
+    }
+
+    /// Whether the player can not be inside the tile, because it either collides with it or dies
+    pub fn is_blocking(&self, tile_type: &TileTypeData) -> bool {
+        self.is_solid(tile_type) || self.is_hazard(tile_type)
+    }
+
+    /// Unknown tiles let all of the sun through, levels using them do not pass validation
+    pub fn sun_transmittance(&self, tile_type: &TileTypeData) -> f32 {
+        self.tile(tile_type)
+            .map_or(1., |tile| tile.sun_transmittance)
+    }
+
+    pub fn heat(&self, tile_type: &TileTypeData) -> Option<HeatSource> {
+        self.tile(tile_type).and_then(|tile| tile.heat)
+    }
+
+    pub fn refreezes(&self, tile_type: &TileTypeData) -> bool {
+        self.tile(tile_type).is_some_and(|tile| tile.refreezes)
+    }
+
+    pub fn light(&self, tile_type: &TileTypeData) -> Option<f32> {
+        self.tile(tile_type).and_then(|tile| tile.light)
+    }
+
+    /// The tiles of a level as they are during the night run
+    pub fn night_tiles(&self, tiles: &[TileData]) -> Vec<TileData> {
+        tiles
+            .iter()
+            .filter_map(|tile_data| {
+                let at_night = self
+                    .tile(&tile_data.tile_type)
+                    .map_or(&NightChange::Unchanged, |tile| &tile.at_night);
+
+                match at_night {
+                    NightChange::Unchanged => Some(tile_data.clone()),
+                    NightChange::Becomes(tile_type) => Some(TileData {
+                        tile_type: tile_type.clone(),
+                        ..tile_data.clone()
+                    }),
+                    NightChange::Vanishes => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct TileRegistryAssetLoader;
+
+#[derive(Debug, Error)]
+pub enum TileRegistryAssetLoaderError {
+    #[error("Could not parse json: {0}")]
+    SerdeParse(#[from] serde_json::Error),
+    #[error("Could not read file: {0}")]
+    IO(#[from] io::Error),
+    #[error("Could not read tile registry: {0}")]
+    ReadRegistry(#[from] ReadAssetBytesError),
+    #[error("Tile registry has more than one tile called {0}")]
+    DuplicateTile(TileTypeData),
+    #[error("Tile registry has more than one overlay called {0}")]
+    DuplicateOverlay(OverlayData),
+    #[error("Tile registry uses atlas index {0} more than once")]
+    DuplicateAtlasIndex(u32),
+    #[error("Tile {0} has a sun transmittance of {1}, it must be between 0 and 1")]
+    InvalidSunTransmittance(TileTypeData, f32),
+    #[error("Tile {0} has a light radius of {1}, it must be more than 0")]
+    InvalidLightRadius(TileTypeData, f32),
+    #[error("Tile {0} becomes {1} at night, which is not in the tile registry")]
+    UnknownNightTile(TileTypeData, TileTypeData),
+    #[error("Tile {0} becomes {1} at night, which changes again at night")]
+    ChainedNightTile(TileTypeData, TileTypeData),
+    #[error("Tile {0} has the run length encoded code '{1}', it must be a lower case letter")]
+    InvalidRLECode(TileTypeData, char),
+    #[error("Tile registry uses the run length encoded code '{0}' more than once")]
+    DuplicateRLECode(char),
+    #[error("Tile registry has more than one overlay written in upper case")]
+    MultipleUpperCaseOverlays,
+    #[error("Ascii glyph '{0}' is reserved for the spawn location, snow or empty tiles")]
+    ReservedASCIIGlyph(char),
+    #[error("Tile registry uses the ascii glyph '{0}' more than once")]
+    DuplicateASCIIGlyph(char),
+    #[error("Overlay {0} is drawn on {1} in ascii levels, which is not in the tile registry")]
+    UnknownGlyphTile(OverlayData, TileTypeData),
+}
+
+pub fn parse_tile_registry(bytes: &[u8]) -> Result<TileRegistry, TileRegistryAssetLoaderError> {
+    let registry: TileRegistry = serde_json::from_slice(bytes)?;
+
+    let mut names = HashSet::new();
+    let mut overlay_names = HashSet::new();
+    let mut atlas_indices = HashSet::new();
+    let mut rle_codes = HashSet::new();
+    let mut ascii_glyphs = HashSet::new();
+
+    let mut check_ascii_glyph = |glyph: char| {
+        if RESERVED_GLYPHS.contains(&glyph) {
+            Err(TileRegistryAssetLoaderError::ReservedASCIIGlyph(glyph))
+        } else if !ascii_glyphs.insert(glyph) {
+            Err(TileRegistryAssetLoaderError::DuplicateASCIIGlyph(glyph))
+        } else {
+            Ok(())
+        }
+    };
+
+    for tile in &registry.tiles {
+        if !names.insert(&tile.name) {
+            return Err(TileRegistryAssetLoaderError::DuplicateTile(
+                tile.name.clone(),
+            ));
+        }
+
+        if !atlas_indices.insert(tile.atlas_index) {
+            return Err(TileRegistryAssetLoaderError::DuplicateAtlasIndex(
+                tile.atlas_index,
+            ));
+        }
+
+        if !(0. ..=1.).contains(&tile.sun_transmittance) {
+            return Err(TileRegistryAssetLoaderError::InvalidSunTransmittance(
+                tile.name.clone(),
+                tile.sun_transmittance,
+            ));
+        }
+
+        if let Some(light) = tile.light.filter(|light| light.is_nan() || *light <= 0.) {
+            return Err(TileRegistryAssetLoaderError::InvalidLightRadius(
+                tile.name.clone(),
+                light,
+            ));
+        }
+
+        if let Some(code) = tile.rle_code {
+            // digits are run lengths and upper case letters carry an overlay
+            if !code.is_ascii_lowercase() {
+                return Err(TileRegistryAssetLoaderError::InvalidRLECode(
+                    tile.name.clone(),
+                    code,
+                ));
+            }
+
+            if !rle_codes.insert(code) {
+                return Err(TileRegistryAssetLoaderError::DuplicateRLECode(code));
+            }
+        }
+
+        if let Some(glyph) = tile.ascii_glyph {
+            check_ascii_glyph(glyph)?;
+        }
+    }
+
+    // night tiles are only changed once, so the tile they become has to stay as it is
+    for tile in &registry.tiles {
+        let NightChange::Becomes(night_tile) = &tile.at_night else {
+            continue;
+        };
+
+        match registry.tile(night_tile) {
+            None => {
+                return Err(TileRegistryAssetLoaderError::UnknownNightTile(
+                    tile.name.clone(),
+                    night_tile.clone(),
+                ));
+            }
+            Some(night_definition) if night_definition.at_night != NightChange::Unchanged => {
+                return Err(TileRegistryAssetLoaderError::ChainedNightTile(
+                    tile.name.clone(),
+                    night_tile.clone(),
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for overlay in &registry.overlays {
+        if !overlay_names.insert(&overlay.name) {
+            return Err(TileRegistryAssetLoaderError::DuplicateOverlay(
+                overlay.name.clone(),
+            ));
+        }
+
+        if !atlas_indices.insert(overlay.atlas_index) {
+            return Err(TileRegistryAssetLoaderError::DuplicateAtlasIndex(
+                overlay.atlas_index,
+            ));
+        }
+
+        if let Some(overlay_glyph) = &overlay.ascii_glyph {
+            check_ascii_glyph(overlay_glyph.glyph)?;
+
+            if registry.tile(&overlay_glyph.on).is_none() {
+                return Err(TileRegistryAssetLoaderError::UnknownGlyphTile(
+                    overlay.name.clone(),
+                    overlay_glyph.on.clone(),
+                ));
+            }
+        }
+    }
+
+    if registry
+        .overlays
+        .iter()
+        .filter(|overlay| overlay.rle_upper_case)
+        .count()
+        > 1
+    {
+        return Err(TileRegistryAssetLoaderError::MultipleUpperCaseOverlays);
+    }
+
+    Ok(registry)
+}
+
+/// Reads the registry straight from the asset directory, for the tools that run without the bevy app
+pub fn read_tile_registry_from_disk() -> Result<TileRegistry, TileRegistryAssetLoaderError> {
+    let bytes = fs::read(Path::new(ASSET_DIRECTORY).join(TILE_REGISTRY_PATH))?;
+
+    parse_tile_registry(bytes.as_slice())
+}
+
+/// Reads the registry from within the loader of another asset, which makes that asset reload with the registry
+pub async fn read_tile_registry(
+    load_context: &mut LoadContext<'_>,
+) -> Result<TileRegistry, TileRegistryAssetLoaderError> {
+    let bytes = load_context.read_asset_bytes(TILE_REGISTRY_PATH).await?;
+
+    parse_tile_registry(bytes.as_slice())
+}
+
+impl AssetLoader for TileRegistryAssetLoader {
+    type Asset = TileRegistry;
+    type Settings = ();
+    type Error = TileRegistryAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TileRegistry, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        parse_tile_registry(bytes.as_slice())
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["registry.json"]
+    }
+}
+
+#[derive(Resource, Default)]
+struct TileRegistryHandleRes(Handle<TileRegistry>);
+
+fn load_tile_registry(
+    asset_server: Res<AssetServer>,
+    mut tile_registry_handle: ResMut<TileRegistryHandleRes>,
+) {
+    tile_registry_handle.0 = asset_server.load(TILE_REGISTRY_PATH);
+}
+
+fn tile_registry_ready(
+    mut commands: Commands,
+    mut asset_event_reader: EventReader<AssetEvent<TileRegistry>>,
+    tile_registry_handle: Res<TileRegistryHandleRes>,
+    tile_registries: Res<Assets<TileRegistry>>,
+) {
+    for asset_event in asset_event_reader.read() {
+        if !asset_event.is_loaded_with_dependencies(&tile_registry_handle.0)
+            && !asset_event.is_modified(&tile_registry_handle.0)
+        {
+            continue;
+        }
+
+        if let Some(tile_registry) = tile_registries.get(&tile_registry_handle.0) {
+            commands.insert_resource(tile_registry.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn tile(name: &str, atlas_index: u32) -> Value {
+        json!({"name": name, "atlas_index": atlas_index, "solid": true, "friction": 0.5})
+    }
+
+    fn overlay(name: &str, atlas_index: u32) -> Value {
+        json!({"name": name, "atlas_index": atlas_index})
+    }
+
+    fn parse(
+        tiles: Vec<Value>,
+        overlays: Vec<Value>,
+    ) -> Result<TileRegistry, TileRegistryAssetLoaderError> {
+        let bytes = serde_json::to_vec(&json!({"tiles": tiles, "overlays": overlays})).unwrap();
+
+        parse_tile_registry(bytes.as_slice())
+    }
+
+    #[test]
+    fn shipped_registry_is_accepted() {
+        read_tile_registry_from_disk().unwrap();
+    }
+
+    #[test]
+    fn duplicate_tile_name_is_rejected() {
+        assert!(matches!(
+            parse(vec![tile("Dirt", 0), tile("Dirt", 1)], vec![]),
+            Err(TileRegistryAssetLoaderError::DuplicateTile(name)) if name.0 == "Dirt"
+        ));
+    }
+
+    #[test]
+    fn duplicate_overlay_name_is_rejected() {
+        assert!(matches!(
+            parse(vec![tile("Dirt", 0)], vec![overlay("Grass", 1), overlay("Grass", 2)]),
+            Err(TileRegistryAssetLoaderError::DuplicateOverlay(name)) if name.0 == "Grass"
+        ));
+    }
+
+    #[test]
+    fn atlas_index_shared_by_tile_and_overlay_is_rejected() {
+        assert!(matches!(
+            parse(vec![tile("Dirt", 0)], vec![overlay("Grass", 0)]),
+            Err(TileRegistryAssetLoaderError::DuplicateAtlasIndex(0))
+        ));
+    }
+
+    #[test]
+    fn duplicate_rle_code_is_rejected() {
+        let mut dirt = tile("Dirt", 0);
+        dirt["rle_code"] = json!("d");
+        let mut dust = tile("Dust", 1);
+        dust["rle_code"] = json!("d");
+
+        assert!(matches!(
+            parse(vec![dirt, dust], vec![]),
+            Err(TileRegistryAssetLoaderError::DuplicateRLECode('d'))
+        ));
+    }
+
+    #[test]
+    fn rle_code_that_is_not_a_lower_case_letter_is_rejected() {
+        for code in ["D", "4", "."] {
+            let mut dirt = tile("Dirt", 0);
+            dirt["rle_code"] = json!(code);
+
+            assert!(
+                matches!(
+                    parse(vec![dirt], vec![]),
+                    Err(TileRegistryAssetLoaderError::InvalidRLECode(..))
+                ),
+                "{} was accepted",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn more_than_one_upper_case_overlay_is_rejected() {
+        let mut grass = overlay("Grass", 1);
+        grass["rle_upper_case"] = json!(true);
+        let mut moss = overlay("Moss", 2);
+        moss["rle_upper_case"] = json!(true);
+
+        assert!(matches!(
+            parse(vec![tile("Dirt", 0)], vec![grass, moss]),
+            Err(TileRegistryAssetLoaderError::MultipleUpperCaseOverlays)
+        ));
+    }
+
+    #[test]
+    fn duplicate_ascii_glyph_is_rejected() {
+        let mut dirt = tile("Dirt", 0);
+        dirt["ascii_glyph"] = json!("d");
+        let mut grass = overlay("Grass", 1);
+        grass["ascii_glyph"] = json!({"glyph": "d", "on": "Dirt"});
+
+        assert!(matches!(
+            parse(vec![dirt], vec![grass]),
+            Err(TileRegistryAssetLoaderError::DuplicateASCIIGlyph('d'))
+        ));
+    }
+
+    #[test]
+    fn reserved_ascii_glyph_is_rejected() {
+        for glyph in RESERVED_GLYPHS {
+            let mut dirt = tile("Dirt", 0);
+            dirt["ascii_glyph"] = json!(glyph);
+
+            assert!(
+                matches!(
+                    parse(vec![dirt], vec![]),
+                    Err(TileRegistryAssetLoaderError::ReservedASCIIGlyph(reserved)) if reserved == glyph
+                ),
+                "'{}' was accepted",
+                glyph
+            );
+
+            let mut grass = overlay("Grass", 1);
+            grass["ascii_glyph"] = json!({"glyph": glyph, "on": "Dirt"});
+
+            assert!(
+                matches!(
+                    parse(vec![tile("Dirt", 0)], vec![grass]),
+                    Err(TileRegistryAssetLoaderError::ReservedASCIIGlyph(reserved)) if reserved == glyph
+                ),
+                "'{}' was accepted for an overlay",
+                glyph
+            );
+        }
+    }
+
+    #[test]
+    fn overlay_glyph_on_unknown_tile_is_rejected() {
+        let mut grass = overlay("Grass", 1);
+        grass["ascii_glyph"] = json!({"glyph": "\"", "on": "Marble"});
+
+        assert!(matches!(
+            parse(vec![tile("Dirt", 0)], vec![grass]),
+            Err(TileRegistryAssetLoaderError::UnknownGlyphTile(_, tile_type)) if tile_type.0 == "Marble"
+        ));
+    }
+
+    #[test]
+    fn sun_transmittance_above_one_is_rejected() {
+        let mut glass = tile("Glass", 0);
+        glass["sun_transmittance"] = json!(1.5);
+
+        assert!(matches!(
+            parse(vec![glass], vec![]),
+            Err(TileRegistryAssetLoaderError::InvalidSunTransmittance(..))
+        ));
+    }
+
+    #[test]
+    fn light_radius_of_zero_is_rejected() {
+        let mut campfire = tile("Campfire", 0);
+        campfire["light"] = json!(0.);
+
+        assert!(matches!(
+            parse(vec![campfire], vec![]),
+            Err(TileRegistryAssetLoaderError::InvalidLightRadius(..))
+        ));
+    }
+
+    #[test]
+    fn night_tile_that_is_not_in_the_registry_is_rejected() {
+        let mut water = tile("Water", 0);
+        water["at_night"] = json!({"Becomes": "Ice"});
+
+        assert!(matches!(
+            parse(vec![water], vec![]),
+            Err(TileRegistryAssetLoaderError::UnknownNightTile(..))
+        ));
+    }
+
+    #[test]
+    fn night_tile_that_changes_again_is_rejected() {
+        let mut water = tile("Water", 0);
+        water["at_night"] = json!({"Becomes": "Ice"});
+        let mut ice = tile("Ice", 1);
+        ice["at_night"] = json!("Vanishes");
+
+        assert!(matches!(
+            parse(vec![water, ice], vec![]),
+            Err(TileRegistryAssetLoaderError::ChainedNightTile(..))
+        ));
+    }
+}
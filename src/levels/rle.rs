@@ -0,0 +1,330 @@
+use std::io;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+use crate::levels::data::{LevelData, LocationData, OverlayData, TileData, TileTypeData};
+use crate::levels::migration::{LevelMigrationError, migrate_level};
+use crate::levels::registry::{read_tile_registry, TileRegistry, TileRegistryAssetLoaderError};
+use crate::levels::validation::{InvalidLevelError, validate_level};
+
+const EMPTY_CODE: char = '.';
+
+/// Loads levels stored as a json header line with everything but the tiles, followed by one line per row of tiles
+/// from the top of the level down, one for every row of the level's height. Each row is a run length encoded list of
+/// the `rle_code`s the tile registry gives its tiles, `4d2.s` being four dirt tiles, two empty tiles and one stone
+/// tile. Lower case codes are plain tiles and upper case codes have the registry's upper case overlay on top
+#[derive(Default)]
+pub struct LevelRLEAssetLoader;
+
+#[derive(Debug, Error)]
+pub enum LevelRLEAssetLoaderError {
+    #[error("Could not parse json header: {0}")]
+    SerdeParse(#[from] serde_json::Error),
+    #[error("Could not read file: {0}")]
+    IO(#[from] io::Error),
+    #[error("Could not migrate level: {0}")]
+    Migration(#[from] LevelMigrationError),
+    #[error("Could not load tile registry: {0}")]
+    TileRegistry(#[from] TileRegistryAssetLoaderError),
+    #[error("Level data was invalid: {0}")]
+    InvalidLevel(#[from] InvalidLevelError),
+    #[error("Level is missing its json header line")]
+    MissingHeader,
+    #[error("Unknown tile code '{0}' in row {1}")]
+    UnknownTileCode(char, u32),
+    #[error("Run length without a tile code at the end of row {0}")]
+    DanglingRunLength(u32),
+    #[error("Row {0} is wider than the level's width of {1}")]
+    RowTooWide(u32, u32),
+    #[error("Level has {0} rows of tiles but a height of {1}")]
+    RowCountMismatch(usize, u32),
+    #[error("{0} with {1:?} at {2} has no run length encoded tile code")]
+    UnrepresentableTile(TileTypeData, Option<OverlayData>, LocationData),
+    #[error("Tile at {0} is outside of the {width}x{height} level", width = .1.x, height = .1.y)]
+    TileOutOfBounds(LocationData, UVec2),
+}
+
+impl AssetLoader for LevelRLEAssetLoader {
+    type Asset = LevelData;
+    type Settings = ();
+    type Error = LevelRLEAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelData, Self::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await?;
+
+        let tile_registry = read_tile_registry(load_context).await?;
+
+        let level_data = deserialize_level_rle(&text, &tile_registry)?;
+
+        validate_level(&level_data, &tile_registry)?;
+
+        Ok(level_data)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lvl.rle"]
+    }
+}
+
+/// Parses a run length encoded level, migrating its header from older versions, without validating it
+pub fn deserialize_level_rle(
+    text: &str,
+    tile_registry: &TileRegistry,
+) -> Result<LevelData, LevelRLEAssetLoaderError> {
+    let mut lines = text.lines();
+    let header = lines
+        .next()
+        .ok_or(LevelRLEAssetLoaderError::MissingHeader)?;
+
+    let mut header_json: serde_json::Value = serde_json::from_str(header)?;
+    migrate_level(&mut header_json)?;
+
+    if let Some(header) = header_json.as_object_mut() {
+        header.insert("tiles".into(), serde_json::Value::Array(vec![]));
+    }
+
+    let mut level_data: LevelData = serde_json::from_value(header_json)?;
+
+    let rows = lines.collect::<Vec<_>>();
+
+    if rows.len() != level_data.height as usize {
+        return Err(LevelRLEAssetLoaderError::RowCountMismatch(
+            rows.len(),
+            level_data.height,
+        ));
+    }
+
+    for (row_index, row) in rows.into_iter().enumerate() {
+        let y = level_data.height - 1 - row_index as u32;
+        let mut x = 0;
+        let mut run_length: Option<u32> = None;
+
+        for code in row.trim_end().chars() {
+            if let Some(digit) = code.to_digit(10) {
+                // a run too long for a u32 is far wider than any level
+                let next_run_length = run_length
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|run_length| run_length.checked_add(digit))
+                    .ok_or(LevelRLEAssetLoaderError::RowTooWide(y, level_data.width))?;
+
+                run_length = Some(next_run_length);
+                continue;
+            }
+
+            let tile = tile_from_code(code, tile_registry)
+                .ok_or(LevelRLEAssetLoaderError::UnknownTileCode(code, y))?;
+            let run = run_length.take().unwrap_or(1);
+
+            // checked before the tiles are added, so a huge run can not fill memory before validation sees it
+            if run > level_data.width - x {
+                return Err(LevelRLEAssetLoaderError::RowTooWide(y, level_data.width));
+            }
+
+            for _ in 0..run {
+                if let Some((tile_type, over)) = &tile {
+                    level_data.tiles.push(TileData {
+                        tile_type: tile_type.clone(),
+                        off: LocationData::new(x, y),
+                        over: over.clone(),
+                    });
+                }
+
+                x += 1;
+            }
+        }
+
+        if run_length.is_some() {
+            return Err(LevelRLEAssetLoaderError::DanglingRunLength(y));
+        }
+    }
+
+    Ok(level_data)
+}
+
+/// Writes a level as its json header followed by its rows of tiles, failing for tiles that have no code or lie outside
+/// of the level rather than dropping them
+pub fn serialize_level_rle(
+    level_data: &LevelData,
+    tile_registry: &TileRegistry,
+) -> Result<String, LevelRLEAssetLoaderError> {
+    if let Some(tile) = level_data
+        .tiles
+        .iter()
+        .find(|tile| !level_data.is_in_bounds(tile.off))
+    {
+        return Err(LevelRLEAssetLoaderError::TileOutOfBounds(
+            tile.off,
+            level_data.size(),
+        ));
+    }
+
+    let mut header_json = serde_json::to_value(level_data)?;
+
+    if let Some(header) = header_json.as_object_mut() {
+        header.remove("tiles");
+    }
+
+    let mut text = serde_json::to_string(&header_json)?;
+    text.push('\n');
+
+    let tile_map = level_data
+        .tiles
+        .iter()
+        .map(|tile| (tile.off, tile))
+        .collect::<HashMap<_, _>>();
+
+    for y in (0..level_data.height).rev() {
+        let mut runs: Vec<(char, u32)> = vec![];
+
+        for x in 0..level_data.width {
+            let code = match tile_map.get(&LocationData::new(x, y)) {
+                Some(tile) => tile_code(tile, tile_registry)?,
+                None => EMPTY_CODE,
+            };
+
+            match runs.last_mut() {
+                Some((last_code, run_length)) if *last_code == code => *run_length += 1,
+                _ => runs.push((code, 1)),
+            }
+        }
+
+        for (code, run_length) in runs {
+            if run_length > 1 {
+                text.push_str(&run_length.to_string());
+            }
+
+            text.push(code);
+        }
+
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+fn tile_code(
+    tile: &TileData,
+    tile_registry: &TileRegistry,
+) -> Result<char, LevelRLEAssetLoaderError> {
+    let code = tile_registry
+        .tile(&tile.tile_type)
+        .and_then(|definition| definition.rle_code);
+    let upper_case_overlay = tile_registry
+        .upper_case_overlay()
+        .map(|overlay| &overlay.name);
+
+    let unrepresentable = || {
+        LevelRLEAssetLoaderError::UnrepresentableTile(
+            tile.tile_type.clone(),
+            tile.over.clone(),
+            tile.off,
+        )
+    };
+
+    match (code, &tile.over) {
+        (Some(code), None) => Ok(code),
+        (Some(code), Some(over)) if upper_case_overlay == Some(over) => {
+            Ok(code.to_ascii_uppercase())
+        }
+        _ => Err(unrepresentable()),
+    }
+}
+
+/// The tile and overlay for a code, or none for empty tiles
+#[allow(clippy::type_complexity)]
+fn tile_from_code(
+    code: char,
+    tile_registry: &TileRegistry,
+) -> Option<Option<(TileTypeData, Option<OverlayData>)>> {
+    if code == EMPTY_CODE {
+        return Some(None);
+    }
+
+    let tile = tile_registry.tile_by_rle_code(code.to_ascii_lowercase())?;
+
+    let over = if code.is_ascii_uppercase() {
+        Some(tile_registry.upper_case_overlay()?.name.clone())
+    } else {
+        None
+    };
+
+    Some(Some((tile.name.clone(), over)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels::test_support::{assert_same_level, shipped_level, tile_registry};
+
+    use super::*;
+
+    /// A shipped level with grass on more than one kind of tile
+    const SHIPPED_LEVEL_PATH: &str = "level/level1.lvl.json";
+
+    #[test]
+    fn shipped_level_survives_round_trip() {
+        let tile_registry = tile_registry();
+        let level_data = shipped_level(SHIPPED_LEVEL_PATH);
+
+        let text = serialize_level_rle(&level_data, &tile_registry).unwrap();
+        let parsed = deserialize_level_rle(&text, &tile_registry).unwrap();
+
+        assert_same_level(&parsed, &level_data);
+    }
+
+    #[test]
+    fn run_past_width_is_rejected() {
+        let text = "{\"version\":2,\"width\":4,\"height\":1,\"spawn_location\":\"0,0\"}\n2d3s\n";
+
+        assert!(matches!(
+            deserialize_level_rle(text, &tile_registry()),
+            Err(LevelRLEAssetLoaderError::RowTooWide(0, 4))
+        ));
+    }
+
+    #[test]
+    fn overflowing_run_is_rejected() {
+        let text =
+            "{\"version\":2,\"width\":4,\"height\":1,\"spawn_location\":\"0,0\"}\n99999999999d\n";
+
+        assert!(matches!(
+            deserialize_level_rle(text, &tile_registry()),
+            Err(LevelRLEAssetLoaderError::RowTooWide(0, 4))
+        ));
+    }
+
+    #[test]
+    fn dangling_run_length_is_rejected() {
+        let text = "{\"version\":2,\"width\":4,\"height\":1,\"spawn_location\":\"0,0\"}\n2d2\n";
+
+        assert!(matches!(
+            deserialize_level_rle(text, &tile_registry()),
+            Err(LevelRLEAssetLoaderError::DanglingRunLength(0))
+        ));
+    }
+
+    #[test]
+    fn tile_out_of_bounds_is_rejected() {
+        let mut level_data = shipped_level(SHIPPED_LEVEL_PATH);
+        let off = LocationData::new(0, level_data.height);
+        level_data.tiles.push(TileData {
+            tile_type: "Stone".into(),
+            off,
+            over: None,
+        });
+
+        assert!(matches!(
+            serialize_level_rle(&level_data, &tile_registry()),
+            Err(LevelRLEAssetLoaderError::TileOutOfBounds(loc, _)) if loc == off
+        ));
+    }
+}
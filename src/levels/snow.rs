@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::levels::data::{LevelData, LocationData};
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::levels::{SunTransmittance, TileLevelLoadedEvent};
+use crate::math::tile_pos_to_world_pos;
+use crate::player::Player;
+use crate::player::respawn::RespawnPlayerEvent;
+use crate::z_indices::SNOW_Z_INDEX;
+
+/// Width and height of the snow sprite
+const SNOW_SIZE: f32 = 4.;
+
+pub struct SnowPlugin;
+
+impl Plugin for SnowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerCollectSnowEvent>()
+            .add_systems(Update, (spawn_snow, player_collect_snow).chain());
+    }
+}
+
+/// Snow lying in the level, gone once collected until the player respawns
+#[derive(Component)]
+pub struct Snow(pub LocationData);
+
+#[derive(Event)]
+pub struct PlayerCollectSnowEvent(pub LocationData);
+
+/// Lays all of the level's snow out again when a different level is built and whenever the player respawns, so every
+/// attempt gets the same snow. The tile maps are also rebuilt between day and night and on hot reloads, which leave
+/// the snow collected so far alone
+#[allow(clippy::too_many_arguments)]
+fn spawn_snow(
+    mut commands: Commands,
+    snow_query: Query<Entity, With<Snow>>,
+    mut tile_level_loaded_event: EventReader<TileLevelLoadedEvent>,
+    mut respawn_player_event: EventReader<RespawnPlayerEvent>,
+    mut laid_level: Local<Option<AssetId<LevelData>>>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
+    asset_server: Res<AssetServer>,
+) {
+    let level_id = level_data_handle.0.as_ref().map(|handle| handle.id());
+
+    let level_changed = tile_level_loaded_event.read().count() > 0 && *laid_level != level_id;
+    let respawned = respawn_player_event.read().count() > 0;
+
+    if !level_changed && !respawned {
+        return;
+    }
+
+    for entity in snow_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    *laid_level = level_id;
+
+    let Some(level_data) = level_id.and_then(|id| level_data_assets.get(id)) else {
+        return;
+    };
+
+    for loc in &level_data.snow {
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("image/object/mini_snow.png"),
+                transform: Transform::from_translation(tile_pos_to_world_pos(
+                    (*loc).into(),
+                    SNOW_Z_INDEX,
+                )),
+                ..default()
+            },
+            Collider::ball(SNOW_SIZE / 2.),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            // the sensor is hit by the rays towards the sun as well, but snow this small casts no shade
+            SunTransmittance(1.),
+            Snow(*loc),
+        ));
+    }
+}
+
+/// Only collisions between the player and [`Snow`] count, the snow is removed as soon as it is touched
+fn player_collect_snow(
+    mut commands: Commands,
+    mut collision_event: EventReader<CollisionEvent>,
+    player_query: Query<(), With<Player>>,
+    snow_query: Query<&Snow>,
+    mut player_collect_snow: EventWriter<PlayerCollectSnowEvent>,
+) {
+    for collision in collision_event.read() {
+        let CollisionEvent::Started(entity_a, entity_b, _) = collision else {
+            continue;
+        };
+
+        let snow_entity = if player_query.contains(*entity_a) {
+            *entity_b
+        } else if player_query.contains(*entity_b) {
+            *entity_a
+        } else {
+            continue;
+        };
+
+        if let Ok(snow) = snow_query.get(snow_entity) {
+            commands.entity(snow_entity).despawn();
+            player_collect_snow.send(PlayerCollectSnowEvent(snow.0));
+        }
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::VecDeque;
+
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::HashMap;
+
+use crate::levels::{HALF_TILE_SIZE, TILE_SIZE};
+use crate::day_night::DayNightState;
+use crate::day_night::sun::sun_at;
+use crate::levels::data::{DayNightCycleData, LevelData, TileTypeData};
+use crate::levels::heat::HeatSource;
+use crate::levels::registry::TileRegistry;
+use crate::math::tile_pos_to_world_pos_2d;
+use crate::PIXELS_PER_METER;
+use crate::player::melting::{MELT_INTERVAL, MeltStage, REFREEZE_INTERVAL};
+use crate::player::movement::{JUMP_POWER, PLAYER_MAX_VELOCITY};
+
+/// Rapier's default gravity, scaled by the pixels per meter the physics plugin is created with
+const GRAVITY: f32 = 9.81 * PIXELS_PER_METER;
+const SIMULATION_STEP: f32 = 1. / 60.;
+const MAX_SIMULATION_TIME: f32 = 5.;
+/// Days and nights a run of a level with a forgiving sun is searched for. The snowman can stay out in the sun for good
+/// there, so the time under it keeps wrapping around and the states on each tile would never stop growing
+const FORGIVING_SEARCH_DAYS: f32 = 2.;
+/// Distance between the points sampled along the way to the sun, small enough not to skip a tile the ray only clips
+const SUN_RAY_STEP: f32 = TILE_SIZE / 8.;
+
+pub struct SolverReport {
+    /// Melt stage of the player when reaching the exit during the day, none if the exit can not be reached
+    pub day: Option<MeltStage>,
+    /// Whether the exit can be reached at night, starting with the melt stage the day run ended with. Levels with a
+    /// timed cycle have no night run, so this is the same as the day run
+    pub night: bool,
+}
+
+/// Checks whether the exit can be reached during the day, and then again at night with the melt stage the day run
+/// ended with, on the tiles as they are at night. Heat sources melt the player in both runs. Levels with a timed cycle
+/// are only played once, starting at sunrise, and are solved as if their tiles never changed. Melting into a puddle is
+/// a death, unless the level has a forgiving sun. Snow is left out, so a level that can only be finished by collecting
+/// it is reported as unsolvable. The player is simulated as a box the size of its collider, jumping and falling at full
+/// speed, so this is an approximation of the real physics rather than a replay of it
+pub fn solve_level(level_data: &LevelData, tile_registry: &TileRegistry) -> SolverReport {
+    let level = SolverLevel::new(level_data, tile_registry);
+
+    let day = level.search(MeltStage::None, true);
+    let night = match level_data.day_night_cycle {
+        DayNightCycleData::PerRun => day.is_some_and(|stage| {
+            let night_level_data = LevelData {
+                tiles: tile_registry.night_tiles(&level_data.tiles),
+                ..level_data.clone()
+            };
+
+            SolverLevel::new(&night_level_data, tile_registry)
+                .search(stage, false)
+                .is_some()
+        }),
+        DayNightCycleData::Timed => day.is_some(),
+    };
+
+    SolverReport { day, night }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct MeltState {
+    stage: MeltStage,
+    time_under_sun: f32,
+    /// Seconds since the run started, which is where the sun is in the sky
+    elapsed: f32,
+}
+
+impl MeltState {
+    /// Whether this state can do everything the other one can. Being less melted is not enough on its own, as the
+    /// snowman shrinks while it melts and only fits through some gaps once it has. Neither is it enough when arriving
+    /// later, as the sun and the clouds that shade from it have moved on by then
+    fn dominates(&self, other: &Self) -> bool {
+        self.half_height() <= other.half_height()
+            && self.stage <= other.stage
+            && self.time_under_sun <= other.time_under_sun
+            && self.elapsed <= other.elapsed
+    }
+
+    /// The melt rate is how many seconds of sunlight each second is worth, zero resets the time like the shade does
+    fn advance(&mut self, delta_seconds: f32, melt_rate: f32) {
+        self.elapsed += delta_seconds;
+
+        if melt_rate == 0. {
+            self.time_under_sun = 0.;
+        } else {
+            self.time_under_sun += delta_seconds * melt_rate;
+
+            if self.time_under_sun >= MELT_INTERVAL {
+                self.time_under_sun = 0.;
+                self.stage = self.stage.next();
+            }
+        }
+    }
+
+    fn max_velocity(&self) -> f32 {
+        PLAYER_MAX_VELOCITY * self.stage.get_speed_multiplier()
+    }
+
+    fn half_height(&self) -> f32 {
+        let dimensions = self.stage.get_collider_dimensions();
+        dimensions.x + dimensions.y
+    }
+
+    fn half_width(&self) -> f32 {
+        self.stage.get_collider_dimensions().y
+    }
+}
+
+enum Outcome {
+    /// Standing in the empty tile, on top of the tile below it
+    Land(IVec2, MeltState),
+    Exit(MeltState),
+    Death,
+}
+
+struct SolverLevel<'a> {
+    level_data: &'a LevelData,
+    tile_registry: &'a TileRegistry,
+    tiles: HashMap<IVec2, &'a TileTypeData>,
+    heat_sources: Vec<(Vec2, HeatSource)>,
+}
+
+impl<'a> SolverLevel<'a> {
+    fn new(level_data: &'a LevelData, tile_registry: &'a TileRegistry) -> Self {
+        let mut tiles = HashMap::new();
+        let mut heat_sources = Vec::new();
+
+        for tile in &level_data.tiles {
+            let tile_pos = IVec2::new(tile.off.x as i32, tile.off.y as i32);
+
+            tiles.insert(tile_pos, &tile.tile_type);
+
+            if let Some(heat_source) = tile_registry.heat(&tile.tile_type) {
+                heat_sources.push((tile_pos_to_world_pos_2d(tile.off.into()), heat_source));
+            }
+        }
+
+        Self {
+            level_data,
+            tile_registry,
+            tiles,
+            heat_sources,
+        }
+    }
+
+    fn is_solid(&self, tile_pos: IVec2) -> bool {
+        self.tiles
+            .get(&tile_pos)
+            .is_some_and(|tile_type| self.tile_registry.is_solid(tile_type))
+    }
+
+    fn is_hazard(&self, tile_pos: IVec2) -> bool {
+        self.tiles
+            .get(&tile_pos)
+            .is_some_and(|tile_type| self.tile_registry.is_hazard(tile_type))
+    }
+
+    fn is_refreezing(&self, tile_pos: IVec2) -> bool {
+        self.tiles
+            .get(&tile_pos)
+            .is_some_and(|tile_type| self.tile_registry.refreezes(tile_type))
+    }
+
+    fn sun_transmittance(&self, tile_pos: IVec2) -> f32 {
+        self.tiles.get(&tile_pos).map_or(1., |tile_type| {
+            self.tile_registry.sun_transmittance(tile_type)
+        })
+    }
+
+    /// Fraction of the sun left after passing through every tile between the position and the sun, found by walking
+    /// towards the sun until above the top of the level and every cloud. A cloud in the way blocks all of it
+    fn sunlight(&self, center_pos: Vec2, direction: Vec2, elapsed: f32) -> f32 {
+        let start_tile_pos = world_to_tile_pos(center_pos);
+        let top = self
+            .level_data
+            .clouds
+            .iter()
+            .flat_map(|cloud| {
+                cloud
+                    .path
+                    .iter()
+                    .map(|loc| tile_pos_to_world_pos_2d((*loc).into()).y + cloud.size().y / 2.)
+            })
+            .fold(self.level_data.height as f32 * TILE_SIZE, f32::max);
+
+        let mut sunlight = 1.;
+        let mut last_tile_pos = start_tile_pos;
+        let mut ray_pos = center_pos;
+
+        while ray_pos.y < top && sunlight > 0. {
+            ray_pos += direction * SUN_RAY_STEP;
+            let tile_pos = world_to_tile_pos(ray_pos);
+
+            if self
+                .level_data
+                .clouds
+                .iter()
+                .any(|cloud| cloud.contains(elapsed, ray_pos))
+            {
+                return 0.;
+            }
+
+            if tile_pos != last_tile_pos {
+                sunlight *= self.sun_transmittance(tile_pos);
+                last_tile_pos = tile_pos;
+            }
+        }
+
+        sunlight
+    }
+
+    /// Seconds of sunlight each second at the player's center is worth, from the sun during the day and from every heat
+    /// source in range
+    fn melt_rate(&self, center_pos: Vec2, elapsed: f32, daytime: bool) -> f32 {
+        let run = if daytime {
+            DayNightState::Day
+        } else {
+            DayNightState::Night
+        };
+
+        let (direction, intensity) = sun_at(self.level_data, elapsed, run);
+
+        let sun = if intensity > 0. {
+            self.sunlight(center_pos, direction, elapsed) * intensity
+        } else {
+            0.
+        };
+
+        let heat: f32 = self
+            .heat_sources
+            .iter()
+            .map(|(heat_pos, heat_source)| heat_source.rate_at(center_pos.distance(*heat_pos)))
+            .sum();
+
+        sun + heat
+    }
+
+    /// A puddle can not move any further, so it dies where it is. Forgiving levels never melt the player that far
+    fn melt_away(&self, outcome: Outcome) -> Outcome {
+        let forgive = |state: MeltState| MeltState {
+            stage: MeltStage::Mostly,
+            ..state
+        };
+
+        match outcome {
+            Outcome::Land(_, state) | Outcome::Exit(state)
+                if state.stage == MeltStage::Puddle && !self.level_data.forgiving_sun =>
+            {
+                Outcome::Death
+            }
+            Outcome::Land(tile_pos, state) if state.stage == MeltStage::Puddle => {
+                Outcome::Land(tile_pos, forgive(state))
+            }
+            Outcome::Exit(state) if state.stage == MeltStage::Puddle => {
+                Outcome::Exit(forgive(state))
+            }
+            outcome => outcome,
+        }
+    }
+
+    fn search(&self, stage: MeltStage, daytime: bool) -> Option<MeltStage> {
+        let state = MeltState {
+            stage,
+            time_under_sun: 0.,
+            elapsed: 0.,
+        };
+
+        let spawn_pos = tile_pos_to_world_pos_2d(self.level_data.spawn_location.into());
+        let feet_pos = spawn_pos - Vec2::new(0., state.half_height());
+
+        let (start_tile, start_state) =
+            match self.melt_away(self.simulate(feet_pos, Vec2::ZERO, state, daytime)) {
+                Outcome::Land(tile_pos, state) => (tile_pos, state),
+                Outcome::Exit(state) => return Some(state.stage),
+                Outcome::Death => return None,
+            };
+
+        // the states reached on each tile, leaving out those that another state on the same tile dominates
+        let mut best: HashMap<IVec2, Vec<MeltState>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let mut exit_stage: Option<MeltStage> = None;
+
+        let time_limit = if self.level_data.forgiving_sun {
+            self.level_data.day_length * 2. * FORGIVING_SEARCH_DAYS
+        } else {
+            f32::INFINITY
+        };
+
+        best.insert(start_tile, vec![start_state]);
+        queue.push_back((start_tile, start_state));
+
+        while let Some((tile_pos, state)) = queue.pop_front() {
+            // a better state reached the tile after this one was queued, so this one has nothing left to find
+            if !best[&tile_pos].contains(&state) {
+                continue;
+            }
+
+            for outcome in self.moves(tile_pos, state, daytime) {
+                match self.melt_away(outcome) {
+                    Outcome::Land(_, next_state) if next_state.elapsed > time_limit => {}
+                    Outcome::Land(next_tile_pos, next_state) => {
+                        let states = best.entry(next_tile_pos).or_default();
+
+                        if states.iter().any(|state| state.dominates(&next_state)) {
+                            continue;
+                        }
+
+                        states.retain(|state| !next_state.dominates(state));
+                        states.push(next_state);
+                        queue.push_back((next_tile_pos, next_state));
+                    }
+                    Outcome::Exit(exit_state) => {
+                        exit_stage = Some(
+                            exit_stage
+                                .map_or(exit_state.stage, |stage| stage.min(exit_state.stage)),
+                        );
+                    }
+                    Outcome::Death => {}
+                }
+            }
+        }
+
+        exit_stage
+    }
+
+    fn moves(&self, tile_pos: IVec2, state: MeltState, daytime: bool) -> Vec<Outcome> {
+        let max_velocity = state.max_velocity();
+        let feet_pos = Vec2::new(
+            tile_pos.x as f32 * TILE_SIZE,
+            tile_pos.y as f32 * TILE_SIZE - HALF_TILE_SIZE,
+        );
+
+        let mut outcomes = Vec::new();
+
+        for direction in [-1., 1.] {
+            outcomes.extend(self.walk(tile_pos, direction, state, daytime));
+
+            // the capsule keeps standing on a ledge until its center is almost past the edge, which lengthens jumps
+            let ledge_pos =
+                feet_pos + Vec2::new(direction * (HALF_TILE_SIZE + state.half_width() - 1.), 0.);
+
+            let jump_pos = if self.is_solid(tile_pos + IVec2::new(direction as i32, -1))
+                || self.body_hits(
+                    ledge_pos.x + direction * state.half_width(),
+                    ledge_pos.y,
+                    state,
+                    |tile_pos| self.is_solid(tile_pos),
+                ) {
+                feet_pos
+            } else {
+                ledge_pos
+            };
+
+            for x_velocity in [max_velocity, max_velocity / 2.] {
+                outcomes.push(self.simulate(
+                    jump_pos,
+                    Vec2::new(direction * x_velocity, JUMP_POWER),
+                    state,
+                    daytime,
+                ));
+            }
+        }
+
+        outcomes.push(self.simulate(feet_pos, Vec2::new(0., JUMP_POWER), state, daytime));
+
+        // standing still on a refreezing tile undoes a melt stage every refreeze interval, while the sun and the clouds
+        // move on
+        if state.stage > MeltStage::None && self.is_refreezing(tile_pos - IVec2::Y) {
+            outcomes.push(Outcome::Land(
+                tile_pos,
+                MeltState {
+                    stage: state.stage.previous(),
+                    time_under_sun: 0.,
+                    elapsed: state.elapsed + REFREEZE_INTERVAL,
+                },
+            ));
+        }
+
+        outcomes
+    }
+
+    /// Walks one tile to the side, falling off if there is no ground there
+    fn walk(
+        &self,
+        tile_pos: IVec2,
+        direction: f32,
+        mut state: MeltState,
+        daytime: bool,
+    ) -> Vec<Outcome> {
+        let next_tile_pos = tile_pos + IVec2::new(direction as i32, 0);
+        let feet_y = tile_pos.y as f32 * TILE_SIZE - HALF_TILE_SIZE;
+        let edge_x = next_tile_pos.x as f32 * TILE_SIZE;
+
+        if self.body_hits(edge_x, feet_y, state, |tile_pos| self.is_solid(tile_pos)) {
+            return vec![];
+        }
+
+        if self.body_hits(edge_x, feet_y, state, |tile_pos| self.is_hazard(tile_pos)) {
+            return vec![Outcome::Death];
+        }
+
+        let center_pos = Vec2::new(edge_x, feet_y + state.half_height());
+
+        state.advance(
+            TILE_SIZE / state.max_velocity(),
+            self.melt_rate(center_pos, state.elapsed, daytime),
+        );
+
+        if self.level_data.is_exit(center_pos) {
+            return vec![Outcome::Exit(state)];
+        }
+
+        let ground_pos = next_tile_pos - IVec2::Y;
+
+        if self.is_solid(ground_pos) {
+            return vec![Outcome::Land(next_tile_pos, state)];
+        }
+
+        let max_velocity = state.max_velocity();
+
+        [max_velocity, max_velocity / 2., 0.]
+            .into_iter()
+            .map(|x_velocity| {
+                self.simulate(
+                    Vec2::new(edge_x, feet_y),
+                    Vec2::new(direction * x_velocity, 0.),
+                    state,
+                    daytime,
+                )
+            })
+            .collect()
+    }
+
+    /// Moves the player's feet along a jump or fall until it lands, dies or leaves through the exit
+    fn simulate(
+        &self,
+        mut feet_pos: Vec2,
+        mut velocity: Vec2,
+        mut state: MeltState,
+        daytime: bool,
+    ) -> Outcome {
+        let mut time = 0.;
+
+        while time < MAX_SIMULATION_TIME {
+            time += SIMULATION_STEP;
+
+            if velocity.x != 0. {
+                let next_x = feet_pos.x + velocity.x * SIMULATION_STEP;
+                let leading_edge = next_x + velocity.x.signum() * state.half_width();
+
+                // keep pushing against walls, the player will move past them once high enough
+                if !self.body_hits(leading_edge, feet_pos.y, state, |tile_pos| {
+                    self.is_solid(tile_pos)
+                }) {
+                    feet_pos.x = next_x;
+                }
+            }
+
+            velocity.y -= GRAVITY * SIMULATION_STEP;
+            let next_y = feet_pos.y + velocity.y * SIMULATION_STEP;
+            let column = world_to_tile(feet_pos.x);
+
+            if velocity.y > 0. {
+                let head_row = world_to_tile(next_y + state.half_height() * 2.);
+
+                if self.is_solid(IVec2::new(column, head_row)) {
+                    velocity.y = 0.;
+                } else {
+                    feet_pos.y = next_y;
+                }
+            } else if let Some(landing_pos) = self.landing(feet_pos.x, next_y, state) {
+                return Outcome::Land(landing_pos, state);
+            } else {
+                feet_pos.y = next_y;
+            }
+
+            let half_width = state.half_width();
+
+            if self.body_hits(feet_pos.x - half_width, feet_pos.y, state, |tile_pos| {
+                self.is_hazard(tile_pos)
+            }) || self.body_hits(feet_pos.x + half_width, feet_pos.y, state, |tile_pos| {
+                self.is_hazard(tile_pos)
+            }) {
+                return Outcome::Death;
+            }
+
+            let center_pos = feet_pos + Vec2::new(0., state.half_height());
+
+            if self.level_data.is_below_kill_plane(center_pos) {
+                return Outcome::Death;
+            }
+
+            if self.level_data.is_exit(center_pos) {
+                return Outcome::Exit(state);
+            }
+
+            state.advance(
+                SIMULATION_STEP,
+                self.melt_rate(center_pos, state.elapsed, daytime),
+            );
+        }
+
+        Outcome::Death
+    }
+
+    /// The empty tile the player stands in after its feet reach the given position, checking the tiles under the
+    /// whole width of the player, closest to its center first
+    fn landing(&self, x: f32, feet_y: f32, state: MeltState) -> Option<IVec2> {
+        let column = world_to_tile(x);
+        let feet_row = world_to_tile(feet_y);
+        let half_width = state.half_width();
+
+        let mut columns = vec![column];
+        columns.extend([world_to_tile(x - half_width), world_to_tile(x + half_width)]);
+        columns.dedup();
+
+        columns.into_iter().find_map(|column| {
+            let ground_pos = IVec2::new(column, feet_row);
+            let ground_top = feet_row as f32 * TILE_SIZE + HALF_TILE_SIZE;
+
+            (self.is_solid(ground_pos)
+                && !self.body_hits(column as f32 * TILE_SIZE, ground_top, state, |tile_pos| {
+                    self.is_solid(tile_pos)
+                }))
+            .then_some(ground_pos + IVec2::Y)
+        })
+    }
+
+    /// Whether any tile the player's body covers at the given x coordinate matches
+    fn body_hits(
+        &self,
+        x: f32,
+        feet_y: f32,
+        state: MeltState,
+        matches: impl Fn(IVec2) -> bool,
+    ) -> bool {
+        let column = world_to_tile(x);
+        let bottom_row = world_to_tile(feet_y + 1.);
+        let top_row = world_to_tile(feet_y + state.half_height() * 2. - 1.);
+
+        (bottom_row..=top_row).any(|row| matches(IVec2::new(column, row)))
+    }
+}
+
+fn world_to_tile(position: f32) -> i32 {
+    ((position + HALF_TILE_SIZE) / TILE_SIZE).floor() as i32
+}
+
+fn world_to_tile_pos(position: Vec2) -> IVec2 {
+    IVec2::new(world_to_tile(position.x), world_to_tile(position.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::levels::format::LevelFormat;
+    use crate::levels::manifest::{LEVEL_MANIFEST_PATH, parse_level_manifest};
+    use crate::levels::registry::ASSET_DIRECTORY;
+    use crate::levels::test_support::tile_registry;
+    use crate::levels::validation::validate_level;
+
+    use super::*;
+
+    #[test]
+    fn every_level_in_the_manifest_can_be_finished() {
+        let tile_registry = tile_registry();
+
+        let manifest_bytes =
+            fs::read(Path::new(ASSET_DIRECTORY).join(LEVEL_MANIFEST_PATH)).unwrap();
+        let manifest = parse_level_manifest(manifest_bytes.as_slice()).unwrap();
+
+        for entry in &manifest.levels {
+            let path = Path::new(ASSET_DIRECTORY).join(&entry.path);
+            let format = LevelFormat::from_path(&path)
+                .unwrap_or_else(|| panic!("{} has an unknown level format", entry.path));
+            let bytes = fs::read(&path).unwrap();
+            let level_data = format
+                .deserialize(bytes.as_slice(), &tile_registry)
+                .unwrap_or_else(|err| panic!("{}: {}", entry.path, err));
+
+            if let Err(err) = validate_level(&level_data, &tile_registry) {
+                panic!("{}: {}", entry.path, err);
+            }
+
+            // screens such as the ending have no exit to reach
+            if level_data.exit.is_empty() {
+                continue;
+            }
+
+            let report = solve_level(&level_data, &tile_registry);
+
+            assert!(
+                report.day.is_some(),
+                "{}: exit unreachable during the day",
+                entry.path
+            );
+            assert!(report.night, "{}: exit unreachable at night", entry.path);
+        }
+    }
+}
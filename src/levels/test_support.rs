@@ -0,0 +1,33 @@
+use std::fs;
+use std::path::Path;
+
+use crate::levels::data::{LevelData, TileData};
+use crate::levels::level_loader::deserialize_level_json;
+use crate::levels::registry::{ASSET_DIRECTORY, read_tile_registry_from_disk, TileRegistry};
+
+pub fn tile_registry() -> TileRegistry {
+    read_tile_registry_from_disk().unwrap()
+}
+
+/// Loads a shipped json level, with the path relative to the asset directory
+pub fn shipped_level(path: &str) -> LevelData {
+    let bytes = fs::read(Path::new(ASSET_DIRECTORY).join(path)).unwrap();
+
+    deserialize_level_json(bytes.as_slice()).unwrap()
+}
+
+/// The formats write tiles in the order they lie in the level, rather than the order they were listed in
+pub fn sorted_tiles(level_data: &LevelData) -> Vec<TileData> {
+    let mut tiles = level_data.tiles.clone();
+    tiles.sort_by_key(|tile| (tile.off.y, tile.off.x));
+    tiles
+}
+
+/// Checks everything a level file format has to keep when a level is written to it and read back
+pub fn assert_same_level(parsed: &LevelData, level_data: &LevelData) {
+    assert_eq!(parsed.size(), level_data.size());
+    assert_eq!(parsed.spawn_location, level_data.spawn_location);
+    assert_eq!(parsed.exit, level_data.exit);
+    assert_eq!(parsed.snow, level_data.snow);
+    assert_eq!(sorted_tiles(parsed), sorted_tiles(level_data));
+}
@@ -0,0 +1,476 @@
+use std::io;
+
+use bevy::asset::{AssetLoader, AsyncReadExt, io::Reader, LoadContext, ReadAssetBytesError};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::levels::data::{
+    ExitRegionData, LevelData, LocationData, OverlayData, TileData, TileTypeData,
+};
+use crate::levels::registry::{read_tile_registry, TileRegistryAssetLoaderError};
+use crate::levels::validation::{InvalidLevelError, validate_level};
+
+/// Table used by tiled maps that do not set their own in the loader settings
+pub const DEFAULT_TILED_TABLE_PATH: &str = "level/tiled.table.json";
+
+/// Tiled stores flipping and rotation in the top four bits of every gid
+const GID_FLAGS_MASK: u32 = 0xF000_0000;
+
+const SPAWN_OBJECT: &str = "spawn";
+const EXIT_OBJECT: &str = "exit";
+const SNOW_OBJECT: &str = "snow";
+
+/// Imports maps saved from Tiled as json. Tile layers become the main and overlay maps through a [`TiledTable`], and
+/// objects called `spawn`, `exit` and `snow` (by class or by name) set the spawn location, exit regions and snow to
+/// collect. Maps without exit objects get the default exit, and every other setting is always the default. Only json
+/// maps with CSV tile layers can be read, not the `.tmx` XML tiled saves by default or base64 encoded layers
+#[derive(Default)]
+pub struct LevelTiledAssetLoader;
+
+#[derive(Serialize, Deserialize)]
+pub struct LevelTiledAssetLoaderSettings {
+    /// Asset path of the [`TiledTable`] mapping the map's gids onto tiles
+    pub table_path: String,
+}
+
+impl Default for LevelTiledAssetLoaderSettings {
+    fn default() -> Self {
+        Self {
+            table_path: DEFAULT_TILED_TABLE_PATH.into(),
+        }
+    }
+}
+
+/// Maps tiled gids onto tiles and overlays. Gids are the ones tiled writes into the map, so they include the
+/// tileset's `firstgid`
+#[derive(Deserialize)]
+pub struct TiledTable {
+    #[serde(default = "TiledTable::default_main_layer")]
+    pub main_layer: String,
+    #[serde(default = "TiledTable::default_overlay_layer")]
+    pub overlay_layer: String,
+    pub tiles: HashMap<u32, TileTypeData>,
+    #[serde(default)]
+    pub overlays: HashMap<u32, OverlayData>,
+}
+
+impl TiledTable {
+    fn default_main_layer() -> String {
+        "main".into()
+    }
+
+    fn default_overlay_layer() -> String {
+        "overlay".into()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LevelTiledAssetLoaderError {
+    #[error("Could not parse json: {0}")]
+    SerdeParse(#[from] serde_json::Error),
+    #[error("Could not read file: {0}")]
+    IO(#[from] io::Error),
+    #[error("Could not read tiled table: {0}")]
+    ReadTable(#[from] ReadAssetBytesError),
+    #[error("Could not load tile registry: {0}")]
+    TileRegistry(#[from] TileRegistryAssetLoaderError),
+    #[error("Level data was invalid: {0}")]
+    InvalidLevel(#[from] InvalidLevelError),
+    #[error("Infinite tiled maps are not supported")]
+    InfiniteMap,
+    #[error("Layer '{0}' is compressed or encoded, save it with the CSV layer format")]
+    EncodedLayer(String),
+    #[error("Layer '{0}' has {1} tiles but the map is {2}x{3}")]
+    LayerSizeMismatch(String, usize, u32, u32),
+    #[error("Map has no tile layer called '{0}'")]
+    MissingMainLayer(String),
+    #[error("Gid {0} in layer '{1}' is not in the tiled table")]
+    UnmappedGid(u32, String),
+    #[error("Overlay at {0} has no tile below it")]
+    OverlayWithoutTile(LocationData),
+    #[error("Map has no '{}' object", SPAWN_OBJECT)]
+    MissingSpawn,
+    #[error("Map has more than one '{}' object", SPAWN_OBJECT)]
+    MultipleSpawns,
+    #[error("Object '{0}' is outside of the map")]
+    ObjectOutOfBounds(String),
+}
+
+impl AssetLoader for LevelTiledAssetLoader {
+    type Asset = LevelData;
+    type Settings = LevelTiledAssetLoaderSettings;
+    type Error = LevelTiledAssetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelData, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let table_bytes = load_context
+            .read_asset_bytes(settings.table_path.clone())
+            .await?;
+        let table: TiledTable = serde_json::from_slice(&table_bytes)?;
+
+        let level_data = deserialize_level_tiled(&bytes, &table)?;
+
+        let tile_registry = read_tile_registry(load_context).await?;
+
+        validate_level(&level_data, &tile_registry)?;
+
+        Ok(level_data)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+#[derive(Deserialize)]
+struct TiledMap {
+    width: u32,
+    height: u32,
+    #[serde(rename = "tilewidth")]
+    tile_width: f32,
+    #[serde(rename = "tileheight")]
+    tile_height: f32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    TileLayer {
+        name: String,
+        data: TiledLayerData,
+    },
+    ObjectGroup {
+        objects: Vec<TiledObject>,
+    },
+    /// Image and group layers are ignored
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TiledLayerData {
+    Gids(Vec<u32>),
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    /// Called `class` by tiled 1.9 and `type` by every other version
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+}
+
+impl TiledObject {
+    fn is(&self, kind: &str) -> bool {
+        self.class.eq_ignore_ascii_case(kind) || self.name.eq_ignore_ascii_case(kind)
+    }
+}
+
+/// Imports a tiled json map without validating it
+pub fn deserialize_level_tiled(
+    bytes: &[u8],
+    table: &TiledTable,
+) -> Result<LevelData, LevelTiledAssetLoaderError> {
+    let map: TiledMap = serde_json::from_slice(bytes)?;
+
+    if map.infinite {
+        return Err(LevelTiledAssetLoaderError::InfiniteMap);
+    }
+
+    let mut main_layer = None;
+    let mut overlay_layer = None;
+    let mut objects = vec![];
+
+    for layer in &map.layers {
+        match layer {
+            TiledLayer::TileLayer { name, data } if *name == table.main_layer => {
+                main_layer = Some(layer_gids(&map, name, data)?);
+            }
+            TiledLayer::TileLayer { name, data } if *name == table.overlay_layer => {
+                overlay_layer = Some(layer_gids(&map, name, data)?);
+            }
+            TiledLayer::ObjectGroup {
+                objects: layer_objects,
+            } => objects.extend(layer_objects),
+            _ => {}
+        }
+    }
+
+    let main_layer = main_layer
+        .ok_or_else(|| LevelTiledAssetLoaderError::MissingMainLayer(table.main_layer.clone()))?;
+
+    let mut tiles = vec![];
+    let mut tile_indices = HashMap::new();
+
+    for (loc, gid) in main_layer {
+        let tile_type = table.tiles.get(&gid).ok_or_else(|| {
+            LevelTiledAssetLoaderError::UnmappedGid(gid, table.main_layer.clone())
+        })?;
+
+        tile_indices.insert(loc, tiles.len());
+        tiles.push(TileData {
+            tile_type: tile_type.clone(),
+            off: loc,
+            over: None,
+        });
+    }
+
+    for (loc, gid) in overlay_layer.unwrap_or_default() {
+        let over = table.overlays.get(&gid).ok_or_else(|| {
+            LevelTiledAssetLoaderError::UnmappedGid(gid, table.overlay_layer.clone())
+        })?;
+
+        // overlays are stored on the tile they cover
+        let index = tile_indices
+            .get(&loc)
+            .ok_or(LevelTiledAssetLoaderError::OverlayWithoutTile(loc))?;
+
+        tiles[*index].over = Some(over.clone());
+    }
+
+    let mut spawn_location = None;
+    let mut exit = vec![];
+    let mut snow = vec![];
+
+    for object in objects {
+        if object.is(SPAWN_OBJECT) {
+            let center_x = object.x + object.width / 2.;
+            let center_y = object.y + object.height / 2.;
+
+            let loc = pixel_to_tile(&map, center_x, center_y).ok_or_else(|| {
+                LevelTiledAssetLoaderError::ObjectOutOfBounds(object.name.clone())
+            })?;
+
+            if spawn_location.replace(loc).is_some() {
+                return Err(LevelTiledAssetLoaderError::MultipleSpawns);
+            }
+        } else if object.is(SNOW_OBJECT) {
+            let center_x = object.x + object.width / 2.;
+            let center_y = object.y + object.height / 2.;
+
+            let loc = pixel_to_tile(&map, center_x, center_y).ok_or_else(|| {
+                LevelTiledAssetLoaderError::ObjectOutOfBounds(object.name.clone())
+            })?;
+
+            snow.push(loc);
+        } else if object.is(EXIT_OBJECT) {
+            // tiled measures y down from the top of the map, so the top left corner of the object is the tile with the
+            // smallest x and largest y. Both corners are nudged inwards so edges lying on tile borders stay outside
+            let top_left = pixel_to_tile(&map, object.x + 0.5, object.y + 0.5);
+            let bottom_right = pixel_to_tile(
+                &map,
+                object.x + (object.width - 0.5).max(0.5),
+                object.y + (object.height - 0.5).max(0.5),
+            );
+
+            let (Some(top_left), Some(bottom_right)) = (top_left, bottom_right) else {
+                return Err(LevelTiledAssetLoaderError::ObjectOutOfBounds(
+                    object.name.clone(),
+                ));
+            };
+
+            exit.push(ExitRegionData::Tiles {
+                from: LocationData::new(top_left.x, bottom_right.y),
+                to: LocationData::new(bottom_right.x, top_left.y),
+            });
+        }
+    }
+
+    let mut level_data = LevelData::new(
+        map.width,
+        map.height,
+        spawn_location.ok_or(LevelTiledAssetLoaderError::MissingSpawn)?,
+        tiles,
+    );
+
+    if !exit.is_empty() {
+        level_data.exit = exit;
+    }
+
+    level_data.snow = snow;
+
+    Ok(level_data)
+}
+
+/// The non empty cells of a tile layer, with the flip flags masked off the gids
+fn layer_gids(
+    map: &TiledMap,
+    name: &str,
+    data: &TiledLayerData,
+) -> Result<Vec<(LocationData, u32)>, LevelTiledAssetLoaderError> {
+    let TiledLayerData::Gids(gids) = data else {
+        return Err(LevelTiledAssetLoaderError::EncodedLayer(name.into()));
+    };
+
+    if gids.len() != (map.width * map.height) as usize {
+        return Err(LevelTiledAssetLoaderError::LayerSizeMismatch(
+            name.into(),
+            gids.len(),
+            map.width,
+            map.height,
+        ));
+    }
+
+    Ok(gids
+        .iter()
+        .enumerate()
+        .map(|(index, gid)| (index as u32, gid & !GID_FLAGS_MASK))
+        .filter(|(_, gid)| *gid != 0)
+        .map(|(index, gid)| {
+            // tiled stores rows from the top of the map down, levels count rows from the bottom up
+            let loc = LocationData::new(index % map.width, map.height - 1 - index / map.width);
+
+            (loc, gid)
+        })
+        .collect())
+}
+
+fn pixel_to_tile(map: &TiledMap, x: f32, y: f32) -> Option<LocationData> {
+    let column = (x / map.tile_width).floor();
+    let row = (y / map.tile_height).floor();
+
+    if column < 0. || row < 0. || column >= map.width as f32 || row >= map.height as f32 {
+        return None;
+    }
+
+    Some(LocationData::new(
+        column as u32,
+        map.height - 1 - row as u32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use crate::levels::registry::ASSET_DIRECTORY;
+    use crate::levels::test_support::{assert_same_level, shipped_level};
+    use crate::levels::TILE_SIZE;
+
+    use super::*;
+
+    const SHIPPED_LEVEL_PATH: &str = "level/level1.lvl.json";
+
+    fn table() -> TiledTable {
+        let bytes = fs::read(Path::new(ASSET_DIRECTORY).join(DEFAULT_TILED_TABLE_PATH)).unwrap();
+
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Writes a level the way tiled saves a map drawn with the table's gids, since levels can not be exported to tiled
+    fn serialize_tiled(level_data: &LevelData, table: &TiledTable) -> Vec<u8> {
+        let tile_gid = table
+            .tiles
+            .iter()
+            .map(|(gid, tile_type)| (tile_type, *gid))
+            .collect::<HashMap<_, _>>();
+        let overlay_gid = table
+            .overlays
+            .iter()
+            .map(|(gid, over)| (over, *gid))
+            .collect::<HashMap<_, _>>();
+
+        let cell_count = (level_data.width * level_data.height) as usize;
+        let mut main_gids = vec![0; cell_count];
+        let mut overlay_gids = vec![0; cell_count];
+
+        for tile in &level_data.tiles {
+            let index =
+                ((level_data.height - 1 - tile.off.y) * level_data.width + tile.off.x) as usize;
+
+            main_gids[index] = tile_gid[&tile.tile_type];
+
+            if let Some(over) = &tile.over {
+                overlay_gids[index] = overlay_gid[over];
+            }
+        }
+
+        let object_at = |name: &str, loc: LocationData| {
+            json!({
+                "name": name,
+                "x": loc.x as f32 * TILE_SIZE,
+                "y": (level_data.height - 1 - loc.y) as f32 * TILE_SIZE,
+                "width": TILE_SIZE,
+                "height": TILE_SIZE,
+            })
+        };
+
+        let mut objects = vec![object_at(SPAWN_OBJECT, level_data.spawn_location)];
+        objects.extend(
+            level_data
+                .snow
+                .iter()
+                .map(|loc| object_at(SNOW_OBJECT, *loc)),
+        );
+
+        serde_json::to_vec(&json!({
+            "width": level_data.width,
+            "height": level_data.height,
+            "tilewidth": TILE_SIZE,
+            "tileheight": TILE_SIZE,
+            "infinite": false,
+            "layers": [
+                {"type": "tilelayer", "name": table.main_layer, "data": main_gids},
+                {"type": "tilelayer", "name": table.overlay_layer, "data": overlay_gids},
+                {"type": "objectgroup", "objects": objects},
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn shipped_level_survives_round_trip() {
+        let table = table();
+        let level_data = LevelData {
+            snow: vec![LocationData::new(1, 10)],
+            ..shipped_level(SHIPPED_LEVEL_PATH)
+        };
+
+        let parsed =
+            deserialize_level_tiled(&serialize_tiled(&level_data, &table), &table).unwrap();
+
+        assert_same_level(&parsed, &level_data);
+    }
+
+    #[test]
+    fn map_without_spawn_is_rejected() {
+        let table = table();
+        let bytes = serde_json::to_vec(&json!({
+            "width": 1,
+            "height": 1,
+            "tilewidth": TILE_SIZE,
+            "tileheight": TILE_SIZE,
+            "layers": [{"type": "tilelayer", "name": table.main_layer, "data": [0]}],
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            deserialize_level_tiled(&bytes, &table),
+            Err(LevelTiledAssetLoaderError::MissingSpawn)
+        ));
+    }
+}
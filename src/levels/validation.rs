@@ -0,0 +1,572 @@
+use std::collections::VecDeque;
+use std::fmt::Formatter;
+
+use bevy::math::{IVec2, UVec2};
+use bevy::utils::{HashMap, HashSet};
+use thiserror::Error;
+
+use crate::levels::data::{ExitRegionData, LevelData, LocationData, OverlayData, TileTypeData};
+use crate::levels::registry::TileRegistry;
+
+#[derive(Debug)]
+pub enum InvalidLevelErrorReason {
+    EmptyLevel(UVec2),
+    DuplicateTileLocation(LocationData),
+    UnknownTile(LocationData, TileTypeData),
+    UnknownOverlay(LocationData, OverlayData),
+    TileOutOfBounds(LocationData, UVec2),
+    SpawnOutOfBounds(LocationData, UVec2),
+    SpawnInsideTile(LocationData, TileTypeData),
+    SpawnOverHazard(LocationData, LocationData),
+    SpawnOverNothing(LocationData),
+    InvalidExitRegion(ExitRegionData),
+    ExitUnreachable(LocationData),
+    InvalidDayLength(f32),
+    InvalidSightRadius(f32),
+    InvalidCloud(usize),
+    SnowOutOfBounds(LocationData, UVec2),
+    SnowInsideTile(LocationData, TileTypeData),
+}
+
+impl std::fmt::Display for InvalidLevelErrorReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyLevel(size) => {
+                write!(f, "Level size {}x{} has no tiles in it", size.x, size.y)
+            }
+            Self::DuplicateTileLocation(loc) => {
+                write!(f, "Duplicate tile location at: {}", loc)
+            }
+            Self::UnknownTile(loc, tile_type) => {
+                write!(
+                    f,
+                    "Tile at {} is {} which is not in the tile registry",
+                    loc, tile_type
+                )
+            }
+            Self::UnknownOverlay(loc, over) => {
+                write!(
+                    f,
+                    "Overlay at {} is {} which is not in the tile registry",
+                    loc, over
+                )
+            }
+            Self::TileOutOfBounds(loc, size) => {
+                write!(
+                    f,
+                    "Tile at {} is outside of the {}x{} tile map",
+                    loc, size.x, size.y
+                )
+            }
+            Self::SpawnOutOfBounds(loc, size) => {
+                write!(
+                    f,
+                    "Spawn location {} is outside of the {}x{} tile map",
+                    loc, size.x, size.y
+                )
+            }
+            Self::SpawnInsideTile(loc, tile_type) => {
+                write!(f, "Spawn location {} is inside a {} tile", loc, tile_type)
+            }
+            Self::SpawnOverHazard(loc, hazard_loc) => {
+                write!(
+                    f,
+                    "Spawn location {} is above a hazard at: {}",
+                    loc, hazard_loc
+                )
+            }
+            Self::SpawnOverNothing(loc) => {
+                write!(f, "Spawn location {} has no ground below it", loc)
+            }
+            Self::InvalidExitRegion(exit) => {
+                write!(
+                    f,
+                    "Exit region {:?} has its corners the wrong way round",
+                    exit
+                )
+            }
+            Self::ExitUnreachable(loc) => {
+                write!(f, "No open path from spawn location {} to an exit", loc)
+            }
+            Self::InvalidDayLength(day_length) => {
+                write!(f, "Day length {} has to be more than 0 seconds", day_length)
+            }
+            Self::InvalidSightRadius(sight_radius) => {
+                write!(
+                    f,
+                    "Sight radius {} has to be more than 0 tiles",
+                    sight_radius
+                )
+            }
+            Self::InvalidCloud(index) => {
+                write!(
+                    f,
+                    "Cloud {} needs a path, a speed of 0 or more and a size of more than 0",
+                    index
+                )
+            }
+            Self::SnowOutOfBounds(loc, size) => {
+                write!(
+                    f,
+                    "Snow at {} is outside of the {}x{} tile map",
+                    loc, size.x, size.y
+                )
+            }
+            Self::SnowInsideTile(loc, tile_type) => {
+                write!(f, "Snow at {} is inside a {} tile", loc, tile_type)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub struct InvalidLevelError {
+    pub reasons: Vec<InvalidLevelErrorReason>,
+}
+
+impl std::fmt::Display for InvalidLevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} problem(s) found", self.reasons.len())?;
+
+        for reason in &self.reasons {
+            write!(f, "\n - {}", reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the level for every problem that would either panic while building the tile map, or make the level
+/// impossible to finish, and returns them all instead of stopping at the first one
+pub fn validate_level(
+    level_data: &LevelData,
+    tile_registry: &TileRegistry,
+) -> Result<(), InvalidLevelError> {
+    let mut reasons = Vec::new();
+    let mut tile_locations = HashSet::new();
+    // tiles the player can not stand inside, anything else is as good as empty
+    let mut tile_map = HashMap::new();
+
+    if level_data.width == 0 || level_data.height == 0 {
+        reasons.push(InvalidLevelErrorReason::EmptyLevel(level_data.size()));
+    }
+
+    for tile in level_data.tiles.iter() {
+        if !tile_locations.insert(tile.off) {
+            reasons.push(InvalidLevelErrorReason::DuplicateTileLocation(tile.off));
+        } else if tile_registry.is_blocking(&tile.tile_type) {
+            tile_map.insert(tile.off, &tile.tile_type);
+        }
+
+        if tile_registry.tile(&tile.tile_type).is_none() {
+            reasons.push(InvalidLevelErrorReason::UnknownTile(
+                tile.off,
+                tile.tile_type.clone(),
+            ));
+        }
+
+        if let Some(over) = tile
+            .over
+            .as_ref()
+            .filter(|over| tile_registry.overlay(over).is_none())
+        {
+            reasons.push(InvalidLevelErrorReason::UnknownOverlay(
+                tile.off,
+                over.clone(),
+            ));
+        }
+
+        if !level_data.is_in_bounds(tile.off) {
+            reasons.push(InvalidLevelErrorReason::TileOutOfBounds(
+                tile.off,
+                level_data.size(),
+            ));
+        }
+    }
+
+    for exit in level_data.exit.iter() {
+        if !exit.is_valid() {
+            reasons.push(InvalidLevelErrorReason::InvalidExitRegion(*exit));
+        }
+    }
+
+    if level_data.day_length.is_nan() || level_data.day_length <= 0. {
+        reasons.push(InvalidLevelErrorReason::InvalidDayLength(
+            level_data.day_length,
+        ));
+    }
+
+    if level_data.sight_radius.is_nan() || level_data.sight_radius <= 0. {
+        reasons.push(InvalidLevelErrorReason::InvalidSightRadius(
+            level_data.sight_radius,
+        ));
+    }
+
+    for (index, cloud) in level_data.clouds.iter().enumerate() {
+        if !cloud.is_valid() {
+            reasons.push(InvalidLevelErrorReason::InvalidCloud(index));
+        }
+    }
+
+    for snow in level_data.snow.iter() {
+        if !level_data.is_in_bounds(*snow) {
+            reasons.push(InvalidLevelErrorReason::SnowOutOfBounds(
+                *snow,
+                level_data.size(),
+            ));
+        } else if let Some(tile_type) = tile_map.get(snow) {
+            reasons.push(InvalidLevelErrorReason::SnowInsideTile(
+                *snow,
+                (*tile_type).clone(),
+            ));
+        }
+    }
+
+    let spawn = level_data.spawn_location;
+
+    if !level_data.is_in_bounds(spawn) {
+        reasons.push(InvalidLevelErrorReason::SpawnOutOfBounds(
+            spawn,
+            level_data.size(),
+        ));
+    } else if let Some(tile_type) = tile_map.get(&spawn) {
+        reasons.push(InvalidLevelErrorReason::SpawnInsideTile(
+            spawn,
+            (*tile_type).clone(),
+        ));
+    } else {
+        let ground = (0..spawn.y)
+            .rev()
+            .map(|y| LocationData::new(spawn.x, y))
+            .find_map(|loc| tile_map.get(&loc).map(|tile_type| (loc, tile_type)));
+
+        match ground {
+            Some((ground_loc, tile_type)) if tile_registry.is_hazard(tile_type) => {
+                reasons.push(InvalidLevelErrorReason::SpawnOverHazard(spawn, ground_loc));
+            }
+            Some(_) => {}
+            None => reasons.push(InvalidLevelErrorReason::SpawnOverNothing(spawn)),
+        }
+
+        if !level_data.exit.is_empty() && !is_exit_open(level_data, &tile_map, spawn) {
+            reasons.push(InvalidLevelErrorReason::ExitUnreachable(spawn));
+        }
+    }
+
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidLevelError { reasons })
+    }
+}
+
+/// Flood fills the cells around the spawn that do not block the player, ignoring gravity. This can only prove an exit unreachable when it is
+/// walled off completely, it does not prove that the player can actually get there
+fn is_exit_open(
+    level_data: &LevelData,
+    tile_map: &HashMap<LocationData, &TileTypeData>,
+    spawn: LocationData,
+) -> bool {
+    let size = level_data.size().as_ivec2();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    let spawn = UVec2::from(spawn).as_ivec2();
+    visited.insert(spawn);
+    queue.push_back(spawn);
+
+    while let Some(tile_pos) = queue.pop_front() {
+        if level_data
+            .exit
+            .iter()
+            .any(|exit| exit.overlaps_tile(tile_pos))
+        {
+            return true;
+        }
+
+        for neighbour in
+            [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y].map(|step| tile_pos + step)
+        {
+            // the player can walk off the left and right of the level and jump above the top of it, so the fill goes
+            // one tile past those edges to reach exits placed there. Below the level is the kill plane
+            let in_reach =
+                (-1..=size.x).contains(&neighbour.x) && (0..=size.y).contains(&neighbour.y);
+            let blocked = neighbour.cmpge(IVec2::ZERO).all()
+                && tile_map
+                    .contains_key(&LocationData::new(neighbour.x as u32, neighbour.y as u32));
+
+            if in_reach && !blocked && visited.insert(neighbour) {
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::levels::data::{CloudData, TileData};
+    use crate::levels::test_support::tile_registry;
+    use crate::levels::{HALF_TILE_SIZE, TILE_SIZE};
+
+    use super::*;
+
+    const WIDTH: u32 = 5;
+    const HEIGHT: u32 = 3;
+
+    fn tile(tile_type: &str, x: u32, y: u32) -> TileData {
+        TileData {
+            tile_type: tile_type.into(),
+            off: LocationData::new(x, y),
+            over: None,
+        }
+    }
+
+    /// A level with a floor of dirt, the spawn standing on it and an exit past the right edge
+    fn level() -> LevelData {
+        let floor = (0..WIDTH).map(|x| tile("Dirt", x, 0)).collect();
+
+        LevelData {
+            exit: vec![ExitRegionData::RightOf(WIDTH as f32 * TILE_SIZE)],
+            ..LevelData::new(WIDTH, HEIGHT, LocationData::new(1, 1), floor)
+        }
+    }
+
+    fn assert_rejected(
+        level_data: &LevelData,
+        is_reason: impl Fn(&InvalidLevelErrorReason) -> bool,
+    ) {
+        let err = validate_level(level_data, &tile_registry()).expect_err("level was accepted");
+
+        assert!(err.reasons.iter().any(is_reason), "{}", err);
+    }
+
+    #[test]
+    fn level_with_floor_and_exit_is_accepted() {
+        validate_level(&level(), &tile_registry()).unwrap();
+    }
+
+    #[test]
+    fn exit_left_of_level_is_reachable() {
+        let level_data = LevelData {
+            exit: vec![ExitRegionData::LeftOf(-HALF_TILE_SIZE)],
+            ..level()
+        };
+
+        validate_level(&level_data, &tile_registry()).unwrap();
+    }
+
+    #[test]
+    fn exit_above_level_is_reachable() {
+        let level_data = LevelData {
+            exit: vec![ExitRegionData::Above(
+                HEIGHT as f32 * TILE_SIZE - HALF_TILE_SIZE,
+            )],
+            ..level()
+        };
+
+        validate_level(&level_data, &tile_registry()).unwrap();
+    }
+
+    #[test]
+    fn walled_off_exit_is_rejected() {
+        let mut level_data = LevelData {
+            exit: vec![ExitRegionData::Tiles {
+                from: LocationData::new(3, 1),
+                to: LocationData::new(3, 1),
+            }],
+            ..level()
+        };
+        level_data.tiles.extend([
+            tile("Stone", 2, 1),
+            tile("Stone", 4, 1),
+            tile("Stone", 3, 2),
+        ]);
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::ExitUnreachable(_))
+        });
+    }
+
+    #[test]
+    fn level_without_size_is_rejected() {
+        let level_data = LevelData {
+            width: 0,
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::EmptyLevel(_))
+        });
+    }
+
+    #[test]
+    fn duplicate_tile_location_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles.push(tile("Stone", 0, 0));
+
+        assert_rejected(
+            &level_data,
+            |reason| matches!(reason, InvalidLevelErrorReason::DuplicateTileLocation(loc) if *loc == LocationData::new(0, 0)),
+        );
+    }
+
+    #[test]
+    fn unknown_tile_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles.push(tile("Marble", 3, 2));
+
+        assert_rejected(
+            &level_data,
+            |reason| matches!(reason, InvalidLevelErrorReason::UnknownTile(_, tile_type) if tile_type.0 == "Marble"),
+        );
+    }
+
+    #[test]
+    fn unknown_overlay_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles[0].over = Some("Moss".into());
+
+        assert_rejected(
+            &level_data,
+            |reason| matches!(reason, InvalidLevelErrorReason::UnknownOverlay(_, over) if over.0 == "Moss"),
+        );
+    }
+
+    #[test]
+    fn tile_out_of_bounds_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles.push(tile("Dirt", WIDTH, 0));
+
+        assert_rejected(
+            &level_data,
+            |reason| matches!(reason, InvalidLevelErrorReason::TileOutOfBounds(loc, _) if loc.x == WIDTH),
+        );
+    }
+
+    #[test]
+    fn spawn_out_of_bounds_is_rejected() {
+        let level_data = LevelData {
+            spawn_location: LocationData::new(1, HEIGHT),
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::SpawnOutOfBounds(..))
+        });
+    }
+
+    #[test]
+    fn spawn_inside_tile_is_rejected() {
+        let level_data = LevelData {
+            spawn_location: LocationData::new(1, 0),
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::SpawnInsideTile(..))
+        });
+    }
+
+    #[test]
+    fn spawn_over_hazard_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles[1] = tile("Lava", 1, 0);
+
+        assert_rejected(
+            &level_data,
+            |reason| matches!(reason, InvalidLevelErrorReason::SpawnOverHazard(_, loc) if *loc == LocationData::new(1, 0)),
+        );
+    }
+
+    #[test]
+    fn spawn_over_nothing_is_rejected() {
+        let mut level_data = level();
+        level_data.tiles.remove(1);
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::SpawnOverNothing(_))
+        });
+    }
+
+    #[test]
+    fn exit_region_the_wrong_way_round_is_rejected() {
+        let level_data = LevelData {
+            exit: vec![ExitRegionData::Tiles {
+                from: LocationData::new(3, 1),
+                to: LocationData::new(2, 1),
+            }],
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::InvalidExitRegion(_))
+        });
+    }
+
+    #[test]
+    fn day_length_of_zero_is_rejected() {
+        let level_data = LevelData {
+            day_length: 0.,
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::InvalidDayLength(_))
+        });
+    }
+
+    #[test]
+    fn negative_sight_radius_is_rejected() {
+        let level_data = LevelData {
+            sight_radius: -1.,
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::InvalidSightRadius(_))
+        });
+    }
+
+    #[test]
+    fn cloud_without_path_is_rejected() {
+        let level_data = LevelData {
+            clouds: vec![CloudData {
+                path: vec![],
+                speed: 1.,
+                width: CloudData::default_width(),
+                height: CloudData::default_height(),
+            }],
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::InvalidCloud(0))
+        });
+    }
+
+    #[test]
+    fn snow_out_of_bounds_is_rejected() {
+        let level_data = LevelData {
+            snow: vec![LocationData::new(WIDTH, 1)],
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::SnowOutOfBounds(..))
+        });
+    }
+
+    #[test]
+    fn snow_inside_tile_is_rejected() {
+        let level_data = LevelData {
+            snow: vec![LocationData::new(2, 0)],
+            ..level()
+        };
+
+        assert_rejected(&level_data, |reason| {
+            matches!(reason, InvalidLevelErrorReason::SnowInsideTile(..))
+        });
+    }
+}
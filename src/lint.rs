@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::levels::data::DayNightCycleData;
+use crate::levels::format::LevelFormat;
+use crate::levels::manifest::{LEVEL_MANIFEST_PATH, parse_level_manifest};
+use crate::levels::registry::{
+    ASSET_DIRECTORY, read_tile_registry_from_disk, TILE_REGISTRY_PATH, TileRegistry,
+};
+use crate::levels::solver::solve_level;
+use crate::levels::validation::validate_level;
+
+const LEVEL_DIRECTORY: &str = "assets/level";
+
+/// Runs without creating the bevy app, so it needs neither a window nor a GPU. Prints a report for every level file
+/// and returns false if any of them has a problem
+pub fn lint_levels() -> bool {
+    let mut entries = match fs::read_dir(LEVEL_DIRECTORY) {
+        Ok(read_dir) => read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| LevelFormat::from_path(path).is_some())
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            println!("Could not read {}: {}", LEVEL_DIRECTORY, err);
+            return false;
+        }
+    };
+
+    entries.sort();
+
+    let Some(tile_registry) = lint_tile_registry() else {
+        return false;
+    };
+
+    let mut ok = true;
+
+    for path in &entries {
+        ok &= lint_level_file(path, &tile_registry);
+    }
+
+    ok &= lint_manifest(&entries);
+
+    println!();
+    if ok {
+        println!("All levels OK");
+    } else {
+        println!("Problems found");
+    }
+
+    ok
+}
+
+/// Levels are checked against the tile registry, so none of them can be linted without it
+fn lint_tile_registry() -> Option<TileRegistry> {
+    println!("{}", TILE_REGISTRY_PATH);
+
+    match read_tile_registry_from_disk() {
+        Ok(tile_registry) => {
+            println!(
+                "  {} tile(s), {} overlay(s)",
+                tile_registry.tiles.len(),
+                tile_registry.overlays.len()
+            );
+            Some(tile_registry)
+        }
+        Err(err) => {
+            println!("  could not load tile registry: {}", err);
+            None
+        }
+    }
+}
+
+/// Checks that every level in the manifest exists, and that every level file is listed in the manifest
+fn lint_manifest(level_files: &[PathBuf]) -> bool {
+    println!();
+    println!("{}", LEVEL_MANIFEST_PATH);
+
+    let manifest_path = Path::new(ASSET_DIRECTORY).join(LEVEL_MANIFEST_PATH);
+
+    let manifest = match fs::read(&manifest_path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| parse_level_manifest(bytes.as_slice()).map_err(|err| err.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            println!("  could not load manifest: {}", err);
+            return false;
+        }
+    };
+
+    let mut ok = true;
+    let mut listed_files = Vec::new();
+
+    for (index, entry) in manifest.levels.iter().enumerate() {
+        let path = Path::new(ASSET_DIRECTORY).join(&entry.path);
+
+        println!(
+            "  {}: {} ({}){}",
+            index,
+            entry.name,
+            entry.path,
+            if entry.ending { " [ending]" } else { "" }
+        );
+
+        if !level_files.contains(&path) {
+            println!("    level file does not exist");
+            ok = false;
+        }
+
+        listed_files.push(path);
+    }
+
+    for path in level_files {
+        if !listed_files.contains(path) {
+            println!(
+                "  {} is not listed in the manifest and is never loaded",
+                path.display()
+            );
+            ok = false;
+        }
+    }
+
+    ok
+}
+
+fn lint_level_file(path: &Path, tile_registry: &TileRegistry) -> bool {
+    println!();
+    println!("{}", path.display());
+
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            println!("  could not read file: {}", err);
+            return false;
+        }
+    };
+
+    let Some(format) = LevelFormat::from_path(path) else {
+        println!("  unknown level format");
+        return false;
+    };
+
+    let level_data = match format.deserialize(bytes.as_slice(), tile_registry) {
+        Ok(level_data) => level_data,
+        Err(err) => {
+            println!("  {}", err);
+            return false;
+        }
+    };
+
+    let mut tile_counts = BTreeMap::new();
+    let mut overlay_counts = BTreeMap::new();
+
+    for tile in &level_data.tiles {
+        *tile_counts.entry(tile.tile_type.to_string()).or_insert(0) += 1;
+
+        // overlays are stored on the tile they cover, so an overlay without a tile can not be expressed in a level file
+        if let Some(over) = &tile.over {
+            *overlay_counts.entry(over.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    println!("  spawn: {}", level_data.spawn_location);
+    println!("  tiles: {}", format_counts(&tile_counts));
+    println!("  overlays: {}", format_counts(&overlay_counts));
+
+    if let Err(invalid_level) = validate_level(&level_data, tile_registry) {
+        for reason in invalid_level.reasons {
+            println!("  {}", reason);
+        }
+
+        return false;
+    }
+
+    if level_data.exit.is_empty() {
+        println!("  no problems");
+        return true;
+    }
+
+    let report = solve_level(&level_data, tile_registry);
+
+    match report.day {
+        Some(melt_stage) => println!("  day run: exit reachable, at melt stage {:?}", melt_stage),
+        None => println!("  day run: exit unreachable"),
+    }
+
+    if level_data.day_night_cycle == DayNightCycleData::Timed {
+        println!("  timed cycle, no night run");
+    } else if report.night {
+        println!("  night run: exit reachable");
+    } else {
+        println!("  night run: exit unreachable");
+    }
+
+    report.day.is_some() && report.night
+}
+
+fn format_counts(counts: &BTreeMap<String, u32>) -> String {
+    if counts.is_empty() {
+        return "none".into();
+    }
+
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -19,17 +19,31 @@ use crate::timer::TimerPlugin;
 use crate::win::WinGamePlugin;
 
 mod camera;
+mod convert;
 mod day_night;
 mod debug;
 mod instruction_screen;
 mod levels;
+mod lint;
 mod math;
 mod player;
 mod timer;
 mod win;
 mod z_indices;
 
+pub const PIXELS_PER_METER: f32 = 100.;
+
 fn main() {
+    if env::args().nth(1).as_deref() == Some("lint-levels") {
+        let ok = lint::lint_levels();
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    if env::args().nth(1).as_deref() == Some("convert-level") {
+        let ok = convert::convert_level(env::args().skip(2).collect());
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
     #[cfg(not(debug_assertions))]
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
     #[cfg(debug_assertions)]
@@ -56,7 +70,7 @@ fn main() {
                     ..default()
                 }),
             TilemapPlugin,
-            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.),
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER),
             RapierDebugRenderPlugin::default().disabled(),
         ))
         .add_plugins((
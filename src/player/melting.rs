@@ -1,31 +1,52 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use crate::day_night::DayCycleSet;
+use crate::day_night::sun::{SunDirection, SunIntensity};
+use crate::levels::{Refreezing, SunTransmittance};
+use crate::levels::data::LevelData;
+use crate::levels::heat::HeatSource;
+use crate::levels::level_loader::LevelDataHandleRes;
+use crate::levels::snow::PlayerCollectSnowEvent;
 use crate::player::{CAST_COLLIDER_SCALE, Player, PlayerSprite};
+use crate::player::respawn::KillPlayerEvent;
 use crate::z_indices::PLAYER_Z_INDEX;
 
-const MELT_INTERVAL: f32 = 3.;
+pub const MELT_INTERVAL: f32 = 3.;
+/// Time standing on a refreezing tile takes to undo one melt stage
+pub const REFREEZE_INTERVAL: f32 = 6.;
+/// Time the snowman takes to spread out into a puddle, after which it is gone
+const PUDDLE_TIME: f32 = 1.;
+/// Height of the puddle at the end of the melt, as a fraction of the sprite's height
+const PUDDLE_HEIGHT: f32 = 0.2;
 
 pub struct MeltingPlugin;
 
 impl Plugin for MeltingPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<TimeUnderSun>()
+            .init_resource::<TimeAsPuddle>()
             .add_event::<SetMeltStageEvent>()
             .add_systems(
                 Update,
                 (
-                    (increase_time_under_sun, increase_melt_stage).in_set(DayCycleSet),
+                    (increase_time_under_sun, increase_melt_stage).chain(),
+                    undo_melt_stage_on_snow,
                     update_set_melt_stage,
+                    melt_into_puddle.after(update_set_melt_stage),
                 ),
             );
     }
 }
 
+/// Counts up while the player is in the sun or near heat and down while they refreeze, a melt stage is gained or undone
+/// once it reaches [`MELT_INTERVAL`] or -[`REFREEZE_INTERVAL`]
 #[derive(Resource, Default)]
 pub struct TimeUnderSun(pub f32);
 
+/// Counts up from the moment the player becomes a [`MeltStage::Puddle`]
+#[derive(Resource, Default)]
+struct TimeAsPuddle(f32);
+
 #[derive(Event)]
 pub struct SetMeltStageEvent(pub MeltStage);
 
@@ -35,22 +56,47 @@ impl TimeUnderSun {
     }
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MeltStage {
     #[default]
     None,
     Partial,
     Half,
     Mostly,
+    /// Melted for good, there is no coming back from a puddle
+    Puddle,
 }
 
 impl MeltStage {
+    /// The stage after another [`MELT_INTERVAL`] in the sun
+    pub fn next(&self) -> Self {
+        match self {
+            MeltStage::None => MeltStage::Partial,
+            MeltStage::Partial => MeltStage::Half,
+            MeltStage::Half => MeltStage::Mostly,
+            MeltStage::Mostly => MeltStage::Puddle,
+            MeltStage::Puddle => MeltStage::Puddle,
+        }
+    }
+
+    /// The stage after another [`REFREEZE_INTERVAL`] on a refreezing tile
+    pub fn previous(&self) -> Self {
+        match self {
+            MeltStage::None => MeltStage::None,
+            MeltStage::Partial => MeltStage::None,
+            MeltStage::Half => MeltStage::Partial,
+            MeltStage::Mostly => MeltStage::Half,
+            MeltStage::Puddle => MeltStage::Puddle,
+        }
+    }
+
     pub fn get_speed_multiplier(&self) -> f32 {
         match self {
             MeltStage::None => 1.,
             MeltStage::Partial => 0.8,
             MeltStage::Half => 0.7,
             MeltStage::Mostly => 0.6,
+            MeltStage::Puddle => 0.,
         }
     }
 
@@ -59,7 +105,7 @@ impl MeltStage {
             MeltStage::None => Vec2::splat(16.),
             MeltStage::Partial => Vec2::new(48., 16.),
             MeltStage::Half => Vec2::new(16., 48.),
-            MeltStage::Mostly => Vec2::splat(48.),
+            MeltStage::Mostly | MeltStage::Puddle => Vec2::splat(48.),
         }
     }
 
@@ -68,7 +114,7 @@ impl MeltStage {
             MeltStage::None => Vec2::new(0., 4.5),
             MeltStage::Partial => Vec2::new(0., 6.),
             MeltStage::Half => Vec2::new(0., 7.5),
-            MeltStage::Mostly => Vec2::new(0., 8.5),
+            MeltStage::Mostly | MeltStage::Puddle => Vec2::new(0., 8.5),
         }
     }
 
@@ -77,7 +123,7 @@ impl MeltStage {
             MeltStage::None => Vec2::new(4.5, 6.),
             MeltStage::Partial => Vec2::new(3., 6.),
             MeltStage::Half => Vec2::new(1.5, 6.),
-            MeltStage::Mostly => Vec2::new(0.5, 6.),
+            MeltStage::Mostly | MeltStage::Puddle => Vec2::new(0.5, 6.),
         }
     }
 
@@ -110,6 +156,7 @@ fn update_set_melt_stage(
         ));
 
         sprite_transform.translation = set_melt_stage.0.get_sprite_offset().extend(PLAYER_Z_INDEX);
+        sprite_transform.scale = Vec3::ONE;
 
         commands
             .entity(entity)
@@ -125,29 +172,76 @@ fn update_set_melt_stage(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn increase_time_under_sun(
-    player_query: Query<(Entity, &Transform), With<Player>>,
+    player_query: Query<(Entity, &Player, &Transform)>,
+    sun_transmittance_query: Query<&SunTransmittance>,
+    refreezing_query: Query<(), With<Refreezing>>,
+    heat_source_query: Query<(&HeatSource, &GlobalTransform)>,
     rapier_context: Res<RapierContext>,
+    sun_direction: Res<SunDirection>,
+    sun_intensity: Res<SunIntensity>,
     mut time_under_sun: ResMut<TimeUnderSun>,
     time: Res<Time>,
 ) {
-    let (entity, transform) = player_query.single();
-
-    let ray_start = transform.translation.truncate();
-    let ray_dir = Vec2::Y;
-    let max_time_of_impact = 5000.;
-    let solid = true; // doesn't matter in this case, ray will ALWAYS start in the player's collider, therefore must be excluded by filter below
-    let filter = QueryFilter::default().exclude_collider(entity);
+    let (entity, player, transform) = player_query.single();
 
-    if rapier_context
-        .cast_ray(ray_start, ray_dir, max_time_of_impact, solid, filter)
-        .is_some()
+    if player
+        .ground
+        .is_some_and(|ground| refreezing_query.contains(ground))
     {
-        // hit a collider above, reset time under sun
-        time_under_sun.reset();
+        // standing on a refreezing tile cools the player down even in the sun or next to heat, and undoes any time
+        // spent in it
+        time_under_sun.0 = time_under_sun.0.min(0.) - time.delta_seconds();
+        return;
+    }
+
+    let player_pos = transform.translation.truncate();
+
+    let heat: f32 = heat_source_query
+        .iter()
+        .map(|(heat_source, heat_transform)| {
+            heat_source.rate_at(player_pos.distance(heat_transform.translation().truncate()))
+        })
+        .sum();
+
+    // the sun melts less at dusk and dawn, and not at all at night
+    let sunlight = if sun_intensity.0 > 0. {
+        let ray_start = player_pos;
+        let ray_dir = sun_direction.0;
+        let max_time_of_impact = 5000.;
+        let solid = true; // doesn't matter in this case, ray will ALWAYS start in the player's collider, therefore must be excluded by filter below
+        let filter = QueryFilter::default().exclude_collider(entity);
+        let mut sunlight = sun_intensity.0;
+
+        // every collider between the player and the sun takes its share of it, stop once one of them blocks it completely
+        rapier_context.intersections_with_ray(
+            ray_start,
+            ray_dir,
+            max_time_of_impact,
+            solid,
+            filter,
+            |collider, _| {
+                sunlight *= sun_transmittance_query
+                    .get(collider)
+                    .map_or(0., |sun_transmittance| sun_transmittance.0);
+
+                sunlight > 0.
+            },
+        );
+
+        sunlight
+    } else {
+        0.
+    };
+
+    let melt_rate = sunlight + heat;
+
+    if melt_rate > 0. {
+        time_under_sun.0 += melt_rate * time.delta_seconds();
     } else {
-        // nothing above, keep increasing
-        time_under_sun.0 += time.delta_seconds();
+        // in the shade or at night with no heat around, reset time under sun
+        time_under_sun.reset();
     }
 }
 
@@ -155,19 +249,86 @@ fn increase_melt_stage(
     mut player_query: Query<&Player>,
     mut time_under_sun: ResMut<TimeUnderSun>,
     mut set_melt: EventWriter<SetMeltStageEvent>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
 ) {
     let player = player_query.single_mut();
 
+    if player.melt_stage == MeltStage::Puddle {
+        return;
+    }
+
     if time_under_sun.0 >= MELT_INTERVAL {
         time_under_sun.reset();
 
-        let next_melt_stage = match player.melt_stage {
-            MeltStage::None => MeltStage::Partial,
-            MeltStage::Partial => MeltStage::Half,
-            MeltStage::Half => MeltStage::Mostly,
-            MeltStage::Mostly => MeltStage::Mostly,
-        };
+        let forgiving_sun = level_data_handle
+            .0
+            .as_ref()
+            .and_then(|handle| level_data_assets.get(handle))
+            .is_some_and(|level_data| level_data.forgiving_sun);
+
+        // forgiving levels leave the player mostly melted however long they stay in the sun
+        if player.melt_stage == MeltStage::Mostly && forgiving_sun {
+            return;
+        }
+
+        set_melt.send(SetMeltStageEvent(player.melt_stage.next()));
+    } else if time_under_sun.0 < 0. && player.melt_stage == MeltStage::None {
+        // nothing left to refreeze, time on ice is not banked against the sun
+        time_under_sun.reset();
+    } else if time_under_sun.0 <= -REFREEZE_INTERVAL {
+        time_under_sun.reset();
+
+        set_melt.send(SetMeltStageEvent(player.melt_stage.previous()));
+    }
+}
+
+fn undo_melt_stage_on_snow(
+    player_query: Query<&Player>,
+    mut player_collect_snow: EventReader<PlayerCollectSnowEvent>,
+    mut set_melt: EventWriter<SetMeltStageEvent>,
+) {
+    let player = player_query.single();
+
+    // every piece of snow collected in the same frame undoes a stage of its own
+    let melt_stage = player_collect_snow
+        .read()
+        .fold(player.melt_stage, |melt_stage, _| melt_stage.previous());
+
+    if melt_stage != player.melt_stage {
+        set_melt.send(SetMeltStageEvent(melt_stage));
+    }
+}
+
+/// The last of the snowman spreads out flat along the ground before the player dies
+fn melt_into_puddle(
+    player_query: Query<&Player>,
+    mut player_sprite_query: Query<&mut Transform, With<PlayerSprite>>,
+    mut time_as_puddle: ResMut<TimeAsPuddle>,
+    mut kill_player: EventWriter<KillPlayerEvent>,
+    time: Res<Time>,
+) {
+    let player = player_query.single();
+
+    if player.melt_stage != MeltStage::Puddle {
+        time_as_puddle.0 = 0.;
+        return;
+    }
+
+    time_as_puddle.0 += time.delta_seconds();
+
+    let progress = (time_as_puddle.0 / PUDDLE_TIME).min(1.);
+    let height = 1. - progress * (1. - PUDDLE_HEIGHT);
+
+    let mut sprite_transform = player_sprite_query.single_mut();
+
+    sprite_transform.scale = Vec3::new(1. + progress, height, 1.);
+    // the sprite is 32 pixels high and scales around its center, so it is moved down to keep its bottom on the ground
+    sprite_transform.translation.y = player.melt_stage.get_sprite_offset().y - 16. * (1. - height);
 
-        set_melt.send(SetMeltStageEvent(next_melt_stage));
+    if time_as_puddle.0 >= PUDDLE_TIME {
+        time_as_puddle.0 = 0.;
+        println!("melted");
+        kill_player.send_default();
     }
 }
@@ -6,8 +6,8 @@ use crate::player::movement::MovementPlugin;
 use crate::player::respawn::RespawnPlugin;
 use crate::z_indices::PLAYER_Z_INDEX;
 
-mod melting;
-mod movement;
+pub mod melting;
+pub mod movement;
 pub mod respawn;
 
 const CAST_COLLIDER_SCALE: f32 = 0.9;
@@ -24,6 +24,8 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 pub struct Player {
     on_ground: bool,
+    /// Collider below the player while on the ground
+    ground: Option<Entity>,
     on_wall: bool,
     cast_collider: Collider,
     melt_stage: MeltStage,
@@ -41,6 +43,7 @@ fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     let player = Player {
         on_ground: false,
+        ground: None,
         on_wall: false,
         cast_collider: Collider::capsule_y(cast_collider_dimensions.x, cast_collider_dimensions.y),
         melt_stage,
@@ -60,7 +63,11 @@ fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
             TransformBundle::default(),
             LockedAxes::ROTATION_LOCKED,
             Ccd::enabled(),
-            Friction::new(0.2),
+            // the lower friction wins, so tiles such as ice can be slippery
+            Friction {
+                coefficient: 0.2,
+                combine_rule: CoefficientCombineRule::Min,
+            },
             Restitution::default(),
         ))
         .with_children(|parent| {
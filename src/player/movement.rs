@@ -2,12 +2,13 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::player::Player;
+use crate::player::melting::MeltStage;
 
-const PLAYER_MAX_VELOCITY: f32 = 80.;
+pub const PLAYER_MAX_VELOCITY: f32 = 80.;
 
 /// The acceleration of the player, the player will accelerate to [`PLAYER_MAX_VELOCITY`] in [`PLAYER_MAX_VELOCITY`]/[`ACCELERATION`] seconds
 const ACCELERATION: f32 = 320.;
-const JUMP_POWER: f32 = 300.;
+pub const JUMP_POWER: f32 = 300.;
 const MAX_TOI_GROUNDED: f32 = 1.1;
 const MAX_TOI_ON_WALL: f32 = 2.5;
 
@@ -48,7 +49,9 @@ fn player_move(mut player_query: Query<&mut Player>, keys: Res<ButtonInput<KeyCo
 fn player_jump(mut player_query: Query<(&Player, &mut Velocity)>, keys: Res<ButtonInput<KeyCode>>) {
     let (player, mut velocity) = player_query.single_mut();
 
+    // a puddle can not jump
     if player.on_ground
+        && player.melt_stage != MeltStage::Puddle
         && (keys.just_pressed(KeyCode::KeyW)
             || keys.just_pressed(KeyCode::Space)
             || keys.pressed(KeyCode::ArrowUp))
@@ -90,7 +93,10 @@ fn fix_wall_velocity(
     };
     let collider_shape = &player.cast_collider;
     let cast_options = ShapeCastOptions::with_max_time_of_impact(32.);
-    let query_filter = QueryFilter::default().exclude_collider(entity);
+    // sensors are hazards and tiles the player passes through, neither of them can be stood on or pushed against
+    let query_filter = QueryFilter::default()
+        .exclude_collider(entity)
+        .exclude_sensors();
 
     if let Some((_entity, shape_hit)) = rapier_context.cast_shape(
         cast_start,
@@ -132,9 +138,12 @@ fn check_player_on_ground(
     let cast_direction = Vec2::NEG_Y;
     let collider_shape = &player.cast_collider;
     let cast_options = ShapeCastOptions::with_max_time_of_impact(32.);
-    let query_filter = QueryFilter::default().exclude_collider(entity);
+    // sensors are hazards and tiles the player passes through, neither of them can be stood on or pushed against
+    let query_filter = QueryFilter::default()
+        .exclude_collider(entity)
+        .exclude_sensors();
 
-    if let Some((_entity, shape_hit)) = rapier_context.cast_shape(
+    if let Some((ground_entity, shape_hit)) = rapier_context.cast_shape(
         cast_start,
         shape_rotation,
         cast_direction,
@@ -143,11 +152,13 @@ fn check_player_on_ground(
         query_filter,
     ) {
         player.on_ground = shape_hit.time_of_impact <= MAX_TOI_GROUNDED;
+        player.ground = player.on_ground.then_some(ground_entity);
         // println!(
         //     "toi: {} grounded: {}",
         //     shape_hit.time_of_impact, player.on_ground
         // );
     } else {
         player.on_ground = false;
+        player.ground = None;
     }
 }
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::day_night::{DayNightState, SetDayNightEvent};
-use crate::levels::data::LevelData;
+use crate::levels::data::{DayNightCycleData, LevelData};
 use crate::levels::level_loader::LevelDataHandleRes;
 use crate::levels::LoadNextLevelEvent;
 use crate::math::tile_pos_to_world_pos;
@@ -46,16 +46,28 @@ pub struct RespawnPlayerEvent;
 
 fn check_player_out_of_bounds(
     player_query: Query<&Transform, With<Player>>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
     mut kill_player: EventWriter<KillPlayerEvent>,
     mut player_finish_level: EventWriter<PlayerFinishLevelEvent>,
 ) {
     let transform = player_query.single();
 
-    if transform.translation.y < -20. {
+    let Some(level_data) = level_data_handle
+        .0
+        .as_ref()
+        .and_then(|handle| level_data_assets.get(handle))
+    else {
+        return;
+    };
+
+    let player_pos = transform.translation.truncate();
+
+    if level_data.is_below_kill_plane(player_pos) {
         kill_player.send_default();
     }
 
-    if transform.translation.x > 320. {
+    if level_data.is_exit(player_pos) {
         player_finish_level.send_default();
         println!("fin level");
     }
@@ -75,6 +87,7 @@ fn respawn_player_death(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn respawn_player_finish_level(
     mut player_finish_level_event: EventReader<PlayerFinishLevelEvent>,
     mut respawn_player: EventWriter<RespawnPlayerEvent>,
@@ -82,17 +95,27 @@ fn respawn_player_finish_level(
     mut load_next_level: EventWriter<LoadNextLevelEvent>,
     mut set_melt_stage: EventWriter<SetMeltStageEvent>,
     day_night_state: Res<State<DayNightState>>,
+    level_data_handle: Res<LevelDataHandleRes>,
+    level_data_assets: Res<Assets<LevelData>>,
 ) {
     if player_finish_level_event.read().next().is_some() {
         respawn_player.send_default();
         println!("level fin");
+
+        // levels with a timed cycle are only played once, whatever the time of day
+        let timed = level_data_handle
+            .0
+            .as_ref()
+            .and_then(|handle| level_data_assets.get(handle))
+            .is_some_and(|level_data| level_data.day_night_cycle == DayNightCycleData::Timed);
+
         match day_night_state.get() {
-            DayNightState::Day => {
+            DayNightState::Day if !timed => {
                 println!("replaying at night");
                 // replay same level, but at night
                 set_day_night.send(SetDayNightEvent(DayNightState::Night));
             }
-            DayNightState::Night => {
+            _ => {
                 println!("moving to next level");
                 // next level
                 set_day_night.send(SetDayNightEvent(DayNightState::Day));
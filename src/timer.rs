@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::levels::manifest::{LevelManifest, LevelManifestHandleRes};
 use crate::win::WinGameEvent;
 use crate::z_indices::TEXT_Z_INDEX;
 
@@ -34,6 +35,8 @@ fn end_timer(
     mut win_game_event: EventReader<WinGameEvent>,
     start_time: Res<StartTime>,
     time: Res<Time>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if win_game_event.read().next().is_some() {
         let end_time = time.elapsed_seconds();
@@ -41,9 +44,18 @@ fn end_timer(
 
         let time_rounded = (total_time * 10.).round() / 10.;
 
+        let mut completed_text = format!("Completed in {} seconds!", time_rounded);
+
+        if let Some(par_time) = level_manifests
+            .get(&level_manifest_handle.0)
+            .and_then(|manifest| manifest.total_par_time())
+        {
+            completed_text.push_str(&format!("\nPar: {} seconds", par_time));
+        }
+
         commands.spawn(Text2dBundle {
             text: Text::from_section(
-                format!("Completed in {} seconds!", time_rounded),
+                completed_text,
                 TextStyle {
                     color: Color::srgb_u8(235, 64, 52),
                     ..default()
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 use crate::levels::LoadLevelEvent;
+use crate::levels::manifest::{LevelManifest, LevelManifestHandleRes};
 
 pub struct WinGamePlugin;
 
@@ -17,8 +18,15 @@ pub struct WinGameEvent;
 fn check_win_game(
     mut win_game_event: EventReader<WinGameEvent>,
     mut load_level_event: EventWriter<LoadLevelEvent>,
+    level_manifest_handle: Res<LevelManifestHandleRes>,
+    level_manifests: Res<Assets<LevelManifest>>,
 ) {
     if win_game_event.read().next().is_some() {
-        load_level_event.send(LoadLevelEvent(999));
+        if let Some(ending_level) = level_manifests
+            .get(&level_manifest_handle.0)
+            .and_then(|manifest| manifest.ending_level())
+        {
+            load_level_event.send(LoadLevelEvent(ending_level));
+        }
     }
 }
@@ -1,6 +1,9 @@
 pub const TILE_MAP_Z_INDEX: f32 = 0.;
 pub const TILE_MAP_OVERLAY_Z_INDEX: f32 = 1.;
-pub const PLAYER_Z_INDEX: f32 = 2.;
-pub const SHADOW_Z_INDEX: f32 = 3.;
-pub const TEXT_Z_INDEX: f32 = 4.;
-pub const INSTRUCTION_SCREEN_Z_INDEX: f32 = 5.;
+pub const SNOW_Z_INDEX: f32 = 2.;
+pub const PLAYER_Z_INDEX: f32 = 3.;
+pub const SHADOW_Z_INDEX: f32 = 4.;
+pub const CLOUD_Z_INDEX: f32 = 5.;
+pub const DARKNESS_Z_INDEX: f32 = 6.;
+pub const TEXT_Z_INDEX: f32 = 7.;
+pub const INSTRUCTION_SCREEN_Z_INDEX: f32 = 8.;